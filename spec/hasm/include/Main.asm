@@ -0,0 +1,8 @@
+// Computes RAM[2] = RAM[0] + RAM[1] and RAM[3] = 2 * RAM[2] with macros
+// defined in a subdirectory, one of them reached through a `./` include
+
+.include "lib/arith.asm"
+
+ADD R0, R1, R2
+ADD R2, R2, R3
+HALT
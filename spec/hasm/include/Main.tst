@@ -0,0 +1,11 @@
+load Main.hack,
+output-file Main.out,
+compare-to Main.cmp,
+output-list RAM[0]%D2.6.2 RAM[1]%D2.6.2 RAM[2]%D2.6.2 RAM[3]%D2.6.2;
+
+set RAM[0] 3,
+set RAM[1] 4;
+repeat 20 {
+  ticktock;
+}
+output;
@@ -0,0 +1,11 @@
+// Stores RAM[a] + RAM[b] to RAM[sum]
+.macro ADD a, b, sum
+@a
+D=M
+@b
+D=D+M
+@sum
+M=D
+.endm
+
+.include "./halt.asm"
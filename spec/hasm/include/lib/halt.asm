@@ -0,0 +1,6 @@
+// Loops forever, the label is unique for every expansion
+.macro HALT
+(STOP)
+@STOP
+0;JMP
+.endm
@@ -78,19 +78,14 @@ impl<'a> SymbolTable<'a> {
         self.get_running_index(kind)
     }
 
-    pub fn get_symbol(&self, name: &str) -> Option<&Symbol> {
+    pub fn get_symbol(&self, name: &str) -> Option<&Symbol<'_>> {
         self.subroutine_symbols
             .get(name)
             .or_else(|| self.class_symbols.get(name))
     }
 
     pub fn get_kind_of(&self, name: &str) -> Option<&SymbolKind> {
-        let symbol = self.get_symbol(name);
-
-        match symbol {
-            Option::Some(symbol) => Option::Some(symbol.kind),
-            Option::None => Option::None,
-        }
+        self.get_symbol(name).map(|symbol| symbol.kind)
     }
 
     pub fn get_type_of(&self, name: &str) -> Option<&str> {
@@ -103,12 +98,7 @@ impl<'a> SymbolTable<'a> {
     }
 
     pub fn get_index_of(&self, name: &str) -> Option<u16> {
-        let symbol = self.get_symbol(name);
-
-        match symbol {
-            Option::Some(symbol) => Option::Some(symbol.index),
-            Option::None => Option::None,
-        }
+        self.get_symbol(name).map(|symbol| symbol.index)
     }
 
     pub fn reset_subroutine_table(&mut self) {
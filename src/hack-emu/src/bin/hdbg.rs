@@ -0,0 +1,6 @@
+use hack_emu::config::DebuggerConfig;
+
+fn main() {
+    let config = DebuggerConfig::from_args();
+    hack_emu::debug(config);
+}
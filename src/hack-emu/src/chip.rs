@@ -0,0 +1,67 @@
+use crate::cpu::indexed;
+use crate::read_program;
+use crate::script::Target;
+use hdl::Circuit;
+use std::path::Path;
+
+// Registers and memories inside of the chip are addressed as `DRegister[]` or `RAM16K[3]`
+fn part(name: &str) -> Option<(&str, Option<u16>)> {
+    match name.strip_suffix("[]") {
+        Some(part) => Some((part, None)),
+        None => indexed(name).map(|(part, index)| (part, Some(index))),
+    }
+}
+
+impl Target for Circuit {
+    fn get(&self, name: &str) -> Result<i32, String> {
+        if let Some((value, width)) = self.pin(name) {
+            return Ok(match width {
+                16 => i32::from(value as i16),
+                _ => i32::from(value),
+            });
+        }
+
+        part(name)
+            .and_then(|(part, index)| self.part_value(part, index))
+            .map(|value| i32::from(value as i16))
+            .ok_or_else(|| format!("`{}` has no pin or part `{}`", self.name(), name))
+    }
+
+    fn set(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let value = value as u16;
+        let is_set = self.set_pin(name, value)
+            || part(name).is_some_and(|(part, index)| self.set_part_value(part, index, value));
+
+        if is_set {
+            Ok(())
+        } else {
+            Err(format!("`{}` has no pin or part `{}`", self.name(), name))
+        }
+    }
+
+    fn execute(&mut self, command: &[String], directory: &Path) -> Result<(), String> {
+        match command
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["eval"] => self.eval(),
+            ["tick"] => self.tick(),
+            ["tock"] => self.tock(),
+            ["ticktock"] => {
+                self.tick();
+                self.tock();
+            }
+            [part, "load", file] => {
+                let program = read_program(&directory.join(file))?;
+                if !self.load_part(part, &program) {
+                    return Err(format!("`{}` has no memory `{}`", self.name(), part));
+                }
+            }
+            _ => return Err(format!("unknown command `{}`", command.join(" "))),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::screen::Display;
+
+pub struct Config {
+    pub input_file: String,
+    pub cycles: u64,
+    pub ram: Vec<(u16, u16)>,
+    pub dump: Vec<(u16, u16)>,
+    pub display: Option<Display>,
+    pub screenshot: Option<String>,
+}
+
+impl Config {
+    pub fn new(input_file: &str) -> Config {
+        Config {
+            input_file: String::from(input_file),
+            cycles: 1_000_000,
+            ram: Vec::new(),
+            dump: Vec::new(),
+            display: None,
+            screenshot: None,
+        }
+    }
+
+    pub fn from_args() -> Config {
+        let matches = clap::App::new("hack-emu")
+            .version(clap::crate_version!())
+            .author("Eugene Obrezkov <ghaiklor@gmail.com>")
+            .about("Emulator of the Hack computer")
+            .arg_from_usage("-i --input=<INPUT-FILE> 'Set a program to run: binary .hack, assembly .asm, VM code .vm or a directory of it, or a .tst script'")
+            .arg(
+                clap::Arg::from_usage("-c --cycles=[CYCLES] 'Stop after this many clock cycles unless the program halts earlier'")
+                    .default_value("1000000"),
+            )
+            .arg_from_usage("-s --set=[ADDRESS=VALUE]... 'Set RAM[ADDRESS] before running, e.g. --set 0=256'")
+            .arg_from_usage("-m --memory=[ADDRESS]... 'Print RAM[ADDRESS] after running, ranges like 256-260 are allowed'")
+            .arg(
+                clap::Arg::from_usage("-d --display=[DISPLAY] 'Draw the screen in the terminal and pass key presses to the keyboard, runs until the program halts or Ctrl-C'")
+                    .possible_values(&Display::NAMES),
+            )
+            .arg_from_usage("--screenshot=[FILE] 'Save the screen after running as a .png or .pbm image'")
+            .get_matches();
+
+        let input_file = matches
+            .value_of("input")
+            .expect("Missing --input parameter");
+
+        let mut config = Config::new(input_file);
+        config.cycles = matches
+            .value_of("cycles")
+            .and_then(|cycles| cycles.parse().ok())
+            .expect("--cycles must be a number");
+
+        config.ram = matches
+            .values_of("set")
+            .unwrap_or_default()
+            .map(|assignment| parse_assignment(assignment).expect("--set must be ADDRESS=VALUE"))
+            .collect();
+
+        config.dump = matches
+            .values_of("memory")
+            .unwrap_or_default()
+            .map(|range| parse_range(range).expect("--memory must be ADDRESS or FROM-TO"))
+            .collect();
+
+        config.display = matches.value_of("display").and_then(Display::from_name);
+        config.screenshot = matches.value_of("screenshot").map(String::from);
+
+        config
+    }
+}
+
+fn parse_assignment(assignment: &str) -> Option<(u16, u16)> {
+    let mut parts = assignment.splitn(2, '=');
+    let address = parts.next()?.trim().parse().ok()?;
+    let value = parts.next()?.trim().parse::<i32>().ok()?;
+
+    Some((address, value as u16))
+}
+
+fn parse_range(range: &str) -> Option<(u16, u16)> {
+    let mut parts = range.splitn(2, '-');
+    let from = parts.next()?.trim().parse().ok()?;
+    let to = match parts.next() {
+        Some(to) => to.trim().parse().ok()?,
+        None => from,
+    };
+
+    Some((from, to))
+}
+
+pub struct DebuggerConfig {
+    pub input_file: String,
+    pub debug_info_file: Option<String>,
+    pub cycles: u64,
+}
+
+impl DebuggerConfig {
+    pub fn new(input_file: &str) -> DebuggerConfig {
+        DebuggerConfig {
+            input_file: String::from(input_file),
+            debug_info_file: None,
+            cycles: 10_000_000,
+        }
+    }
+
+    pub fn from_args() -> DebuggerConfig {
+        let matches = clap::App::new("hdbg")
+            .version(clap::crate_version!())
+            .author("Eugene Obrezkov <ghaiklor@gmail.com>")
+            .about("Interactive debugger for Hack machine code, reads commands from stdin")
+            .arg_from_usage("-g --debug-info=[DEBUG-INFO-FILE] 'Read labels, variables and source locations written by hasm --debug-info'")
+            .arg(
+                clap::Arg::from_usage("-c --cycles=[CYCLES] 'Stop `continue` after this many clock cycles without a breakpoint'")
+                    .default_value("10000000"),
+            )
+            .arg_from_usage("<INPUT-FILE> 'Set a program to debug: binary .hack or assembly .asm'")
+            .get_matches();
+
+        let input_file = matches.value_of("INPUT-FILE").expect("Missing input file");
+
+        let mut config = DebuggerConfig::new(input_file);
+        config.debug_info_file = matches.value_of("debug-info").map(String::from);
+        config.cycles = matches
+            .value_of("cycles")
+            .and_then(|cycles| cycles.parse().ok())
+            .expect("--cycles must be a number");
+
+        config
+    }
+}
@@ -0,0 +1,357 @@
+use crate::screen;
+use crate::script::Target;
+use hasm::instructions::*;
+use std::fmt;
+use std::path::Path;
+
+pub const ROM_SIZE: usize = 32768;
+// Data memory, the screen memory map and the keyboard register right after it
+pub const RAM_SIZE: usize = 24577;
+pub const SCREEN: u16 = 16384;
+pub const SCREEN_SIZE: usize = 8192;
+pub const KBD: u16 = 24576;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    IllegalInstruction { pc: u16, word: u16 },
+    RamOutOfRange { pc: u16, address: u16 },
+    RomOutOfRange { pc: u16, address: u16 },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::IllegalInstruction { pc, word } => write!(
+                f,
+                "ROM[{}] = {:016b} does not encode any instruction",
+                pc, word
+            ),
+            Fault::RamOutOfRange { pc, address } => write!(
+                f,
+                "instruction at ROM[{}] accesses RAM[{}] which is out of memory",
+                pc, address
+            ),
+            Fault::RomOutOfRange { pc, address } => write!(
+                f,
+                "instruction at ROM[{}] jumps to ROM[{}] which is out of memory",
+                pc, address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+// The Hack CPU with its ROM and RAM, every `step` is one clock cycle
+pub struct Cpu {
+    pub a: u16,
+    pub d: u16,
+    pub pc: u16,
+    rom: Vec<u16>,
+    program: Vec<Option<Instruction<'static>>>,
+    ram: Vec<u16>,
+    cycles: u64,
+}
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
+impl Cpu {
+    pub fn new() -> Cpu {
+        Cpu {
+            a: 0,
+            d: 0,
+            pc: 0,
+            rom: vec![0; ROM_SIZE],
+            program: vec![Instruction::decode(0); ROM_SIZE],
+            ram: vec![0; RAM_SIZE],
+            cycles: 0,
+        }
+    }
+
+    // Burns the program into ROM, the rest of it is zeroed, and resets the CPU
+    pub fn load(&mut self, program: &[u16]) {
+        for address in 0..ROM_SIZE {
+            self.set_rom(address as u16, program.get(address).copied().unwrap_or(0));
+        }
+
+        self.reset();
+    }
+
+    // Same as the reset pin of the CPU, only PC is affected
+    pub fn reset(&mut self) {
+        self.pc = 0;
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn rom(&self, address: u16) -> u16 {
+        self.rom.get(address as usize).copied().unwrap_or(0)
+    }
+
+    pub fn set_rom(&mut self, address: u16, word: u16) {
+        if let Some(cell) = self.rom.get_mut(address as usize) {
+            *cell = word;
+            self.program[address as usize] = Instruction::decode(word);
+        }
+    }
+
+    pub fn instruction(&self, address: u16) -> Option<Instruction<'static>> {
+        self.program.get(address as usize).copied().flatten()
+    }
+
+    pub fn ram(&self, address: u16) -> u16 {
+        self.ram.get(address as usize).copied().unwrap_or(0)
+    }
+
+    pub fn set_ram(&mut self, address: u16, value: u16) {
+        if let Some(cell) = self.ram.get_mut(address as usize) {
+            *cell = value;
+        }
+    }
+
+    // 256 rows of 32 words, the least significant bit of a word is its leftmost pixel
+    pub fn screen(&self) -> &[u16] {
+        &self.ram[SCREEN as usize..KBD as usize]
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        screen::pixel(self.screen(), x, y)
+    }
+
+    pub fn keyboard(&self) -> u16 {
+        self.ram[KBD as usize]
+    }
+
+    // Code of the key currently pressed, 0 when none
+    pub fn set_keyboard(&mut self, key: u16) {
+        self.ram[KBD as usize] = key;
+    }
+
+    pub fn step(&mut self) -> Result<(), Fault> {
+        let pc = self.pc;
+        let instruction = match self.instruction(pc) {
+            Some(instruction) => instruction,
+            None => {
+                return Err(Fault::IllegalInstruction {
+                    pc,
+                    word: self.rom(pc),
+                })
+            }
+        };
+
+        match instruction {
+            Instruction::A(AInstruction::Literal(address)) => {
+                self.a = address;
+                self.pc = pc + 1;
+            }
+            Instruction::C(instruction) => {
+                let y = if instruction.comp.uses_ram() {
+                    self.read(self.a)?
+                } else {
+                    self.a
+                };
+
+                let out = compute(instruction.comp, self.d, y);
+                let target = self.a;
+
+                if instruction.dest.ram {
+                    self.write(self.a, out)?;
+                }
+
+                if instruction.dest.a_register {
+                    self.a = out;
+                }
+
+                if instruction.dest.d_register {
+                    self.d = out;
+                }
+
+                self.pc = if jumps(&instruction.jump, out) {
+                    if target as usize >= ROM_SIZE {
+                        return Err(Fault::RomOutOfRange {
+                            pc,
+                            address: target,
+                        });
+                    }
+
+                    target
+                } else {
+                    pc + 1
+                };
+            }
+            _ => unreachable!("decoded machine code has only A- and C-instructions"),
+        }
+
+        // The program counter is 15 bits wide and wraps around the end of ROM
+        self.pc %= ROM_SIZE as u16;
+        self.cycles += 1;
+        Ok(())
+    }
+
+    pub fn run(&mut self, cycles: u64) -> Result<(), Fault> {
+        for _ in 0..cycles {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    // Steps until the condition holds or `max_cycles` pass, returns whether the condition held
+    pub fn run_until<F>(&mut self, max_cycles: u64, mut condition: F) -> Result<bool, Fault>
+    where
+        F: FnMut(&Cpu) -> bool,
+    {
+        for _ in 0..max_cycles {
+            self.step()?;
+            if condition(self) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // Whether the CPU spins in the `(END) @END 0;JMP` loop programs finish with
+    pub fn is_halted(&self) -> bool {
+        let pc = self.pc;
+        let loads_own_address = |address: u16| {
+            self.instruction(address) == Some(Instruction::A(AInstruction::Literal(address)))
+        };
+        let jumps_back = |address: u16| match self.instruction(address) {
+            Some(Instruction::C(instruction)) => {
+                instruction.jump.bits() == 0b111 && instruction.dest.is_empty()
+            }
+            _ => false,
+        };
+
+        match pc.checked_sub(1) {
+            Some(start) if loads_own_address(start) && jumps_back(pc) => true,
+            _ => loads_own_address(pc) && jumps_back(pc + 1),
+        }
+    }
+
+    fn read(&self, address: u16) -> Result<u16, Fault> {
+        match self.ram.get(address as usize) {
+            Some(value) => Ok(*value),
+            None => Err(Fault::RamOutOfRange {
+                pc: self.pc,
+                address,
+            }),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u16) -> Result<(), Fault> {
+        match self.ram.get_mut(address as usize) {
+            Some(cell) => {
+                *cell = value;
+                Ok(())
+            }
+            None => Err(Fault::RamOutOfRange {
+                pc: self.pc,
+                address,
+            }),
+        }
+    }
+}
+
+// Variables of the CPU emulator scripts: `A`, `D`, `PC`, `RAM[i]` and `ROM[i]`
+impl Target for Cpu {
+    fn get(&self, name: &str) -> Result<i32, String> {
+        let value = match name {
+            "A" => self.a,
+            "D" => self.d,
+            "PC" => self.pc,
+            _ => match indexed(name) {
+                Some(("RAM", address)) if (address as usize) < RAM_SIZE => self.ram(address),
+                Some(("ROM", address)) if (address as usize) < ROM_SIZE => self.rom(address),
+                _ => return Err(format!("unknown variable `{}`", name)),
+            },
+        };
+
+        Ok(i32::from(value as i16))
+    }
+
+    fn set(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let value = value as u16;
+
+        match name {
+            "A" => self.a = value,
+            "D" => self.d = value,
+            "PC" => self.pc = value % ROM_SIZE as u16,
+            _ => match indexed(name) {
+                Some(("RAM", address)) if (address as usize) < RAM_SIZE => {
+                    self.set_ram(address, value)
+                }
+                Some(("ROM", address)) if (address as usize) < ROM_SIZE => {
+                    self.set_rom(address, value)
+                }
+                _ => return Err(format!("unknown variable `{}`", name)),
+            },
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &[String], _: &Path) -> Result<(), String> {
+        match command
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["tick"] => Ok(()),
+            ["tock"] | ["ticktock"] => self.step().map_err(|fault| fault.to_string()),
+            _ => Err(format!("unknown command `{}`", command.join(" "))),
+        }
+    }
+}
+
+// Splits `RAM[16]` into the name and the index
+pub fn indexed(name: &str) -> Option<(&str, u16)> {
+    let open = name.find('[')?;
+    let index = name[open + 1..].strip_suffix(']')?.parse().ok()?;
+
+    Some((&name[..open], index))
+}
+
+// The ALU, `x` is always D and `y` is either A or M
+fn compute(comp: CCompInstruction, x: u16, y: u16) -> u16 {
+    use CCompInstruction::*;
+
+    match comp {
+        Zero => 0,
+        One => 1,
+        MinusOne => 0xFFFF,
+        DRegister => x,
+        ARegister | RAM => y,
+        NotDRegister => !x,
+        NotARegister | NotRAM => !y,
+        MinusDRegister => x.wrapping_neg(),
+        MinusARegister | MinusRAM => y.wrapping_neg(),
+        DRegisterPlusOne => x.wrapping_add(1),
+        ARegisterPlusOne | RAMPlusOne => y.wrapping_add(1),
+        DRegisterMinusOne => x.wrapping_sub(1),
+        ARegisterMinusOne | RAMMinusOne => y.wrapping_sub(1),
+        DRegisterPlusARegister | DRegisterPlusRAM => x.wrapping_add(y),
+        DRegisterMinusARegister | DRegisterMinusRAM => x.wrapping_sub(y),
+        ARegisterMinusDRegister | RAMMinusDRegister => y.wrapping_sub(x),
+        DRegisterAndARegister | DRegisterAndRAM => x & y,
+        DRegisterOrARegister | DRegisterOrRAM => x | y,
+        DRegisterShiftLeft => x << 1,
+        ARegisterShiftLeft | RAMShiftLeft => y << 1,
+        // Shifts to the right are arithmetic, keeping the sign
+        DRegisterShiftRight => (x as i16 >> 1) as u16,
+        ARegisterShiftRight | RAMShiftRight => (y as i16 >> 1) as u16,
+    }
+}
+
+fn jumps(jump: &CJumpInstruction, out: u16) -> bool {
+    let out = out as i16;
+    (jump.lower_than && out < 0) || (jump.equal && out == 0) || (jump.greater_than && out > 0)
+}
@@ -0,0 +1,544 @@
+use crate::cpu::{indexed, Cpu, RAM_SIZE, ROM_SIZE};
+use crate::read_program;
+use crate::script::Target;
+use hasm::debuginfo::{self, DebugInfo};
+use hasm::instructions::*;
+use hasm::{codegen, macros, parser, symbols};
+use std::fs;
+use std::path::Path;
+
+// Instructions shown before and after the address by `disassemble`
+const CONTEXT: u16 = 5;
+const WORDS_PER_ROW: u16 = 8;
+
+const HELP: &str = "\
+break <address|label>      stop when PC reaches the address, `b` for short
+watch <cell|variable>      stop when the RAM cell changes
+delete [<number>]          remove a breakpoint or a watchpoint, all of them without a number
+step [<count>]             execute instructions one by one, `s` for short
+continue                   run until a breakpoint, a watchpoint or the end, `c` for short
+reset                      set PC to 0, the RAM is left as is
+info registers             show A, D, PC and M
+info breakpoints           list the breakpoints and the watchpoints
+x/<count> <cell>           examine RAM cells, e.g. `x/16 RAM[256]` or `x/4 SP`
+print <name>               show A, D, PC, RAM[i], ROM[i] or a symbol, `p` for short
+set <name> <value>         change A, D, PC, RAM[i] or ROM[i]
+disassemble [<address>]    show instructions around PC or the address
+quit                       leave the debugger, `q` for short";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Point {
+    Break(u16),
+    Watch { address: u16, value: u16 },
+}
+
+// Why `step` or `continue` stopped before running out of instructions
+enum Stop {
+    Breakpoint(usize),
+    Watchpoint(usize, u16, u16),
+    Halted,
+}
+
+// The CPU emulator along with the symbols of the program, commands are executed one line at a
+// time so the debugger can be driven by a terminal or by a script piped into it
+pub struct Debugger {
+    cpu: Cpu,
+    debug_info: DebugInfo,
+    points: Vec<(usize, Point)>,
+    next_id: usize,
+    max_cycles: u64,
+}
+
+impl Debugger {
+    pub fn new(program: &[u16], debug_info: DebugInfo, max_cycles: u64) -> Debugger {
+        let mut cpu = Cpu::new();
+        cpu.load(program);
+
+        Debugger {
+            cpu,
+            debug_info,
+            points: Vec::new(),
+            next_id: 1,
+            max_cycles,
+        }
+    }
+
+    // Assembly is assembled right away to know its labels and variables, machine code gets
+    // them from the debug info `hasm --debug-info` writes
+    pub fn load(
+        file: &str,
+        debug_info_file: Option<&str>,
+        max_cycles: u64,
+    ) -> Result<Debugger, String> {
+        let (program, debug_info) = if file.ends_with(".asm") {
+            assemble(file)?
+        } else {
+            (read_program(Path::new(file))?, DebugInfo::default())
+        };
+
+        let debug_info = match debug_info_file {
+            Some(debug_info_file) => {
+                let source = fs::read_to_string(debug_info_file)
+                    .map_err(|_| format!("could not read `{}`", debug_info_file))?;
+                debuginfo::parse(&source).map_err(|errors| report(debug_info_file, &errors))?
+            }
+            None => debug_info,
+        };
+
+        Ok(Debugger::new(&program, debug_info, max_cycles))
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, arguments) = match words.split_first() {
+            Some((command, arguments)) => (*command, arguments),
+            None => return Ok(String::new()),
+        };
+
+        if let Some(count) = command.strip_prefix("x/") {
+            let count = count
+                .parse()
+                .map_err(|_| format!("`{}` is not a number of cells", count))?;
+            return self.examine(arguments, count);
+        }
+
+        match (command, arguments) {
+            ("break" | "b", [location]) => self.add_break(location),
+            ("watch", [cell]) => self.add_watch(cell),
+            ("delete" | "d", []) => {
+                self.points.clear();
+                Ok(String::from("Deleted all breakpoints and watchpoints"))
+            }
+            ("delete" | "d", [id]) => self.delete(id),
+            ("step" | "s" | "stepi" | "si", []) => self.step(1),
+            ("step" | "s" | "stepi" | "si", [count]) => {
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a number of steps", count))?;
+                self.step(count)
+            }
+            ("continue" | "c", []) => self.resume(),
+            ("reset", []) => {
+                self.cpu.reset();
+                Ok(self.here())
+            }
+            ("info" | "i", ["registers" | "r"]) => Ok(self.registers()),
+            ("info" | "i", ["breakpoints" | "b" | "watchpoints"]) => Ok(self.list_points()),
+            ("x", arguments) => self.examine(arguments, 1),
+            ("print" | "p", [name]) => self.print(name),
+            ("set", [name, value]) => {
+                let value: i32 = value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a number", value))?;
+                self.cpu.set(name, value)?;
+                // Changes made by hand do not trigger watchpoints
+                for (_, point) in self.points.iter_mut() {
+                    if let Point::Watch { address, value } = point {
+                        *value = self.cpu.ram(*address);
+                    }
+                }
+
+                self.print(name)
+            }
+            ("disassemble" | "disas" | "list" | "l", []) => Ok(self.disassemble(self.cpu.pc)),
+            ("disassemble" | "disas" | "list" | "l", [location]) => {
+                let address = self.rom_address(location)?;
+                Ok(self.disassemble(address))
+            }
+            ("help" | "h", []) => Ok(String::from(HELP)),
+            _ => Err(format!("unknown command `{}`, try `help`", words.join(" "))),
+        }
+    }
+
+    fn add_break(&mut self, location: &str) -> Result<String, String> {
+        let address = self.rom_address(location)?;
+        let id = self.add(Point::Break(address));
+
+        Ok(format!("Breakpoint {} at {}", id, self.describe(address)))
+    }
+
+    fn add_watch(&mut self, cell: &str) -> Result<String, String> {
+        let address = self.ram_address(cell)?;
+        let value = self.cpu.ram(address);
+        let id = self.add(Point::Watch { address, value });
+
+        Ok(format!("Watchpoint {} on {}", id, self.cell_name(address)))
+    }
+
+    fn add(&mut self, point: Point) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.points.push((id, point));
+
+        id
+    }
+
+    fn delete(&mut self, id: &str) -> Result<String, String> {
+        let index = id
+            .parse::<usize>()
+            .ok()
+            .and_then(|id| self.points.iter().position(|(point, _)| *point == id))
+            .ok_or_else(|| format!("there is no breakpoint or watchpoint `{}`", id))?;
+
+        self.points.remove(index);
+        Ok(format!("Deleted {}", id))
+    }
+
+    fn step(&mut self, count: u64) -> Result<String, String> {
+        for _ in 0..count {
+            if let Some(stop) = self.advance()?.or_else(|| self.breakpoint()) {
+                return Ok(self.stopped(stop));
+            }
+        }
+
+        Ok(self.here())
+    }
+
+    fn resume(&mut self) -> Result<String, String> {
+        for _ in 0..self.max_cycles {
+            if let Some(stop) = self.advance()?.or_else(|| self.breakpoint()) {
+                return Ok(self.stopped(stop));
+            }
+        }
+
+        Ok(format!(
+            "Stopped after {} cycles without reaching a breakpoint\n{}",
+            self.max_cycles,
+            self.here()
+        ))
+    }
+
+    // Executes a single instruction, reporting faults, halts and changed watched cells
+    fn advance(&mut self) -> Result<Option<Stop>, String> {
+        if self.cpu.is_halted() {
+            return Ok(Some(Stop::Halted));
+        }
+
+        self.cpu.step().map_err(|fault| fault.to_string())?;
+
+        for (id, point) in self.points.iter_mut() {
+            if let Point::Watch { address, value } = point {
+                let new = self.cpu.ram(*address);
+                if new != *value {
+                    let old = *value;
+                    *value = new;
+                    return Ok(Some(Stop::Watchpoint(*id, old, new)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // The breakpoint PC has reached, if any
+    fn breakpoint(&self) -> Option<Stop> {
+        let pc = self.cpu.pc;
+        self.points
+            .iter()
+            .find(|(_, point)| *point == Point::Break(pc))
+            .map(|(id, _)| Stop::Breakpoint(*id))
+    }
+
+    fn stopped(&self, stop: Stop) -> String {
+        let reason = match stop {
+            Stop::Breakpoint(id) => format!("Breakpoint {}", id),
+            Stop::Watchpoint(id, old, new) => {
+                let address = self
+                    .points
+                    .iter()
+                    .find_map(|(point, watch)| match watch {
+                        Point::Watch { address, .. } if *point == id => Some(*address),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+
+                format!(
+                    "Watchpoint {}: {} changed from {} to {}",
+                    id,
+                    self.cell_name(address),
+                    old as i16,
+                    new as i16
+                )
+            }
+            Stop::Halted => format!("Program halted after {} cycles", self.cpu.cycles()),
+        };
+
+        format!("{}\n{}", reason, self.here())
+    }
+
+    // Where PC is, along with the VM command and the Jack line it was generated from
+    fn here(&self) -> String {
+        let pc = self.cpu.pc;
+        let mut output = self.describe(pc);
+
+        if let Some(location) = self.debug_info.rom.get(pc as usize) {
+            if let Some(vm) = &location.vm {
+                output.push_str(&format!("\n    vm: {}", vm));
+            }
+
+            if let Some(jack) = &location.jack {
+                output.push_str(&format!("\n    jack: {}", jack));
+            }
+        }
+
+        output.push('\n');
+        output.push_str(&self.instruction_line(pc));
+        output
+    }
+
+    // `ROM[12] <LOOP+2> at Max.asm:10`
+    fn describe(&self, address: u16) -> String {
+        let mut output = format!("ROM[{}]", address);
+
+        let label = self
+            .debug_info
+            .labels
+            .iter()
+            .filter(|(_, label)| *label <= address)
+            .max_by_key(|(_, label)| *label);
+
+        match label {
+            Some((name, label)) if *label == address => output.push_str(&format!(" <{}>", name)),
+            Some((name, label)) => {
+                output.push_str(&format!(" <{}+{}>", name, address - label));
+            }
+            None => (),
+        }
+
+        if let Some(location) = self.debug_info.rom.get(address as usize) {
+            if !location.file.is_empty() {
+                output.push_str(&format!(" at {}:{}", location.file, location.line));
+            }
+        }
+
+        output
+    }
+
+    fn registers(&self) -> String {
+        let cpu = &self.cpu;
+        let m = if (cpu.a as usize) < RAM_SIZE {
+            format!("{}", cpu.ram(cpu.a) as i16)
+        } else {
+            String::from("out of memory")
+        };
+
+        format!(
+            "A  = {:6}  {:#06x}\nD  = {:6}  {:#06x}\nM  = {}  RAM[{}]\nPC = {}\ncycles = {}",
+            cpu.a as i16,
+            cpu.a,
+            cpu.d as i16,
+            cpu.d,
+            m,
+            cpu.a,
+            self.describe(cpu.pc),
+            cpu.cycles()
+        )
+    }
+
+    fn list_points(&self) -> String {
+        if self.points.is_empty() {
+            return String::from("No breakpoints or watchpoints");
+        }
+
+        self.points
+            .iter()
+            .map(|(id, point)| match point {
+                Point::Break(address) => {
+                    format!("{:<4}breakpoint  {}", id, self.describe(*address))
+                }
+                Point::Watch { address, value } => format!(
+                    "{:<4}watchpoint  {} = {}",
+                    id,
+                    self.cell_name(*address),
+                    *value as i16
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // `x/16 RAM[256]` prints the cells in rows of eight
+    fn examine(&self, arguments: &[&str], count: u16) -> Result<String, String> {
+        let cell = match arguments {
+            [cell] => cell,
+            _ => {
+                return Err(String::from(
+                    "`x` needs a single RAM cell, e.g. `x/16 RAM[256]`",
+                ))
+            }
+        };
+
+        let start = self.ram_address(cell)?;
+        let end = (start as usize + count.max(1) as usize).min(RAM_SIZE) as u16;
+        let rows: Vec<String> = (start..end)
+            .step_by(WORDS_PER_ROW as usize)
+            .map(|row| {
+                let cells: Vec<String> = (row..end.min(row.saturating_add(WORDS_PER_ROW)))
+                    .map(|address| format!("{:7}", self.cpu.ram(address) as i16))
+                    .collect();
+
+                format!("{:<11}{}", format!("RAM[{}]:", row), cells.join(""))
+            })
+            .collect();
+
+        Ok(rows.join("\n"))
+    }
+
+    fn print(&self, name: &str) -> Result<String, String> {
+        if let Ok(value) = self.cpu.get(name) {
+            return Ok(format!("{} = {}", name, value));
+        }
+
+        if let Some(address) = self.debug_info.label(name) {
+            return Ok(format!("{} = ROM[{}]", name, address));
+        }
+
+        let address = self.ram_address(name)?;
+        Ok(format!(
+            "{} = {}",
+            self.cell_name(address),
+            self.cpu.ram(address) as i16
+        ))
+    }
+
+    fn disassemble(&self, address: u16) -> String {
+        let start = address.saturating_sub(CONTEXT);
+        let end = (address as usize + CONTEXT as usize + 1).min(ROM_SIZE) as u16;
+        let mut lines: Vec<String> = Vec::new();
+
+        for address in start..end {
+            for (name, _) in self
+                .debug_info
+                .labels
+                .iter()
+                .filter(|(_, label)| *label == address)
+            {
+                lines.push(format!("({})", name));
+            }
+
+            lines.push(self.instruction_line(address));
+        }
+
+        lines.join("\n")
+    }
+
+    // `=> *  12  @LOOP` with markers for PC and breakpoints
+    fn instruction_line(&self, address: u16) -> String {
+        let is_pc = address == self.cpu.pc;
+        let is_breakpoint = self
+            .points
+            .iter()
+            .any(|(_, point)| *point == Point::Break(address));
+
+        let text = match self.cpu.instruction(address) {
+            Some(Instruction::A(AInstruction::Literal(value))) => match self.symbol(address, value)
+            {
+                Some(name) => format!("@{:<10}// {}", value, name),
+                None => format!("@{}", value),
+            },
+            Some(instruction) => instruction.to_string(),
+            None => format!("<invalid {:016b}>", self.cpu.rom(address)),
+        };
+
+        format!(
+            "{} {} {:5}  {}",
+            if is_pc { "=>" } else { "  " },
+            if is_breakpoint { "*" } else { " " },
+            address,
+            text
+        )
+    }
+
+    // A name the value of an A-instruction at `address` probably stands for. Labels and variables
+    // share addresses, a variable is meant when the next instruction reads or writes M
+    fn symbol(&self, address: u16, value: u16) -> Option<&str> {
+        let uses_ram = match self.cpu.instruction(address.wrapping_add(1)) {
+            Some(Instruction::C(next)) => next.dest.ram || next.comp.uses_ram(),
+            _ => false,
+        };
+
+        let (labels, variables) = (&self.debug_info.labels, &self.debug_info.variables);
+        let mut symbols = if uses_ram {
+            variables.iter().chain(labels)
+        } else {
+            labels.iter().chain(variables)
+        };
+
+        symbols
+            .find(|(_, address)| *address == value)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn cell_name(&self, address: u16) -> String {
+        match self
+            .debug_info
+            .variables
+            .iter()
+            .find(|(_, variable)| *variable == address)
+        {
+            Some((name, _)) => format!("RAM[{}] ({})", address, name),
+            None => format!("RAM[{}]", address),
+        }
+    }
+
+    // `12`, `ROM[12]` or a label
+    fn rom_address(&self, location: &str) -> Result<u16, String> {
+        let address = match indexed(location) {
+            Some(("ROM", address)) => Some(address),
+            _ => location
+                .parse()
+                .ok()
+                .or_else(|| self.debug_info.label(location)),
+        };
+
+        match address {
+            Some(address) if (address as usize) < ROM_SIZE => Ok(address),
+            Some(address) => Err(format!("ROM[{}] is out of memory", address)),
+            None => Err(format!("there is no label `{}`", location)),
+        }
+    }
+
+    // `256`, `RAM[256]`, a predefined symbol like `SP` or a variable
+    fn ram_address(&self, cell: &str) -> Result<u16, String> {
+        let address = match indexed(cell) {
+            Some(("RAM", address)) => Some(address),
+            _ => cell
+                .parse()
+                .ok()
+                .or_else(|| symbols::predefined_address(cell))
+                .or_else(|| self.debug_info.variable(cell)),
+        };
+
+        match address {
+            Some(address) if (address as usize) < RAM_SIZE => Ok(address),
+            Some(address) => Err(format!("RAM[{}] is out of memory", address)),
+            None => Err(format!("there is no variable `{}`", cell)),
+        }
+    }
+}
+
+fn assemble(file: &str) -> Result<(Vec<u16>, DebugInfo), String> {
+    let source = fs::read_to_string(file).map_err(|_| format!("could not read `{}`", file))?;
+    let expansion = macros::expand(&source, file).map_err(|errors| report(file, &errors))?;
+    let statements = parser::parse(&expansion.code, Isa::Extended)
+        .map_err(|errors| report(file, &expansion.locate(errors)))?;
+    let symbol_table =
+        symbols::resolve(&statements).map_err(|errors| report(file, &expansion.locate(errors)))?;
+    let program = codegen::codegen(&statements, &symbol_table)
+        .map_err(|errors| report(file, &expansion.locate(errors)))?;
+
+    let debug_info = debuginfo::collect(&statements, &symbol_table, &expansion);
+    Ok((program, debug_info))
+}
+
+fn report(file: &str, errors: &[hasm::error::Error]) -> String {
+    errors
+        .iter()
+        .map(|error| error.report(file))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
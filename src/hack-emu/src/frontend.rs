@@ -0,0 +1,268 @@
+use crate::cpu::Cpu;
+use crate::os::STEPS_PER_MILLISECOND;
+use crate::screen::Display;
+use crate::vm::Vm;
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FRAME_MILLISECONDS: u64 = 33;
+// The pace `Sys.wait` of the builtin OS assumes, the CPU runs as fast
+const CYCLES_PER_FRAME: u64 = FRAME_MILLISECONDS * STEPS_PER_MILLISECOND;
+// Terminals tell when a key is pressed but not when it is released, so a key stays in KBD
+// for a while, and the auto-repeat of a held key keeps it there
+const KEY_HOLD: Duration = Duration::from_millis(120);
+const CTRL_C: u8 = 3;
+const ESCAPE: u8 = 27;
+
+// Anything with the memory-mapped screen and keyboard of the Hack computer
+pub trait Machine {
+    // Runs at most that many cycles, returns whether the program halted
+    fn run_for(&mut self, cycles: u64) -> Result<bool, String>;
+    fn screen(&self) -> &[u16];
+    fn set_keyboard(&mut self, key: u16);
+}
+
+impl Machine for Cpu {
+    fn run_for(&mut self, cycles: u64) -> Result<bool, String> {
+        self.run_until(cycles, Cpu::is_halted)
+            .map_err(|fault| fault.to_string())
+    }
+
+    fn screen(&self) -> &[u16] {
+        Cpu::screen(self)
+    }
+
+    fn set_keyboard(&mut self, key: u16) {
+        Cpu::set_keyboard(self, key)
+    }
+}
+
+impl Machine for Vm {
+    fn run_for(&mut self, cycles: u64) -> Result<bool, String> {
+        self.run(cycles)?;
+        Ok(self.is_halted())
+    }
+
+    fn screen(&self) -> &[u16] {
+        Vm::screen(self)
+    }
+
+    fn set_keyboard(&mut self, key: u16) {
+        Vm::set_keyboard(self, key)
+    }
+}
+
+// Hack keyboard codes of what a terminal sends, `None` stands for Ctrl-C
+pub fn decode_keys(bytes: &[u8]) -> Vec<Option<u16>> {
+    let mut keys: Vec<Option<u16>> = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let (key, length) = match bytes[index] {
+            CTRL_C => (None, 1),
+            b'\r' | b'\n' => (Some(128), 1),
+            8 | 127 => (Some(129), 1),
+            ESCAPE => match escape_sequence(&bytes[index..]) {
+                (0, length) => {
+                    index += length;
+                    continue;
+                }
+                (key, length) => (Some(key), length),
+            },
+            byte @ 32..=126 => (Some(u16::from(byte)), 1),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        keys.push(key);
+        index += length;
+    }
+
+    keys
+}
+
+// Arrows, Home, End and the others are sent as `ESC [ ...` or `ESC O ...`, a lone ESC is
+// the escape key itself. Returns the code, 0 for keys Hack does not have, and the length
+fn escape_sequence(bytes: &[u8]) -> (u16, usize) {
+    match bytes {
+        [_, b'O', kind, ..] => (letter_key(*kind), 3),
+        // The sequence ends with a byte from `@` to `~`, modifiers like `;5` come before it
+        [_, b'[', rest @ ..] => match rest.iter().position(|byte| (b'@'..=b'~').contains(byte)) {
+            Some(end) if rest[end] == b'~' => {
+                let number = std::str::from_utf8(&rest[..end])
+                    .ok()
+                    .and_then(|numbers| numbers.split(';').next())
+                    .and_then(|number| number.parse::<u16>().ok());
+
+                let key = match number {
+                    Some(1) | Some(7) => 134,
+                    Some(2) => 138,
+                    Some(3) => 139,
+                    Some(4) | Some(8) => 135,
+                    Some(5) => 136,
+                    Some(6) => 137,
+                    // F1 to F5 are 11 to 15, F6 to F10 are 17 to 21, F11 and F12 are 23 and 24
+                    Some(number @ 11..=15) => 141 + number - 11,
+                    Some(number @ 17..=21) => 146 + number - 17,
+                    Some(number @ 23..=24) => 151 + number - 23,
+                    _ => 0,
+                };
+
+                (key, end + 3)
+            }
+            Some(end) => (letter_key(rest[end]), end + 3),
+            None => (140, 1),
+        },
+        _ => (140, 1),
+    }
+}
+
+fn letter_key(letter: u8) -> u16 {
+    match letter {
+        b'A' => 131,
+        b'B' => 133,
+        b'C' => 132,
+        b'D' => 130,
+        b'H' => 134,
+        b'F' => 135,
+        b'P' => 141,
+        b'Q' => 142,
+        b'R' => 143,
+        b'S' => 144,
+        _ => 0,
+    }
+}
+
+// Puts the terminal into raw mode and brings it back when dropped
+struct Terminal {
+    settings: Option<String>,
+}
+
+impl Terminal {
+    fn new() -> Terminal {
+        let settings = stty(&["-g"]).filter(|_| stty(&["raw", "-echo"]).is_some());
+
+        print!("\x1b[?25l\x1b[2J");
+        Terminal { settings }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if let Some(settings) = &self.settings {
+            stty(&[settings.trim()]);
+        }
+
+        print!("\x1b[?25h\r\n");
+        io::stdout().flush().ok();
+    }
+}
+
+fn stty(arguments: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(arguments)
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+// Keys waiting to get into KBD, each one is released before the next one is pressed so
+// programs waiting for a release like `Keyboard.readChar` see all of them
+struct Keyboard {
+    queue: VecDeque<u16>,
+    key: u16,
+    until: Instant,
+}
+
+impl Keyboard {
+    fn press(&mut self, key: u16, now: Instant) {
+        if key == self.key && self.queue.is_empty() {
+            self.until = now + KEY_HOLD;
+        } else {
+            self.queue.push_back(key);
+        }
+    }
+
+    // The key in KBD right now
+    fn update(&mut self, now: Instant) -> u16 {
+        if self.key != 0 && now >= self.until {
+            self.key = 0;
+        } else if self.key == 0 {
+            if let Some(key) = self.queue.pop_front() {
+                self.key = key;
+                self.until = now + KEY_HOLD;
+            }
+        }
+
+        self.key
+    }
+}
+
+// Runs the program until it halts or Ctrl-C is pressed, drawing the screen in the terminal
+// and passing key presses to the keyboard. Returns whether the program halted
+pub fn run(machine: &mut dyn Machine, display: Display) -> Result<bool, String> {
+    let is_interactive = io::stdin().is_terminal();
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    if is_interactive {
+        thread::spawn(move || {
+            let mut buffer = [0u8; 32];
+            while let Ok(length) = io::stdin().read(&mut buffer) {
+                if length == 0 || sender.send(buffer[..length].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let _terminal = Terminal::new();
+    let mut keyboard = Keyboard {
+        queue: VecDeque::new(),
+        key: 0,
+        until: Instant::now(),
+    };
+    let mut previous: Vec<u16> = Vec::new();
+
+    loop {
+        let started = Instant::now();
+
+        for bytes in receiver.try_iter() {
+            for key in decode_keys(&bytes) {
+                match key {
+                    Some(key) => keyboard.press(key, started),
+                    None => return Ok(false),
+                }
+            }
+        }
+
+        machine.set_keyboard(keyboard.update(started));
+        let is_halted = machine.run_for(CYCLES_PER_FRAME)?;
+
+        if machine.screen() != previous.as_slice() {
+            previous = machine.screen().to_vec();
+            let frame = display.render(&previous).join("\r\n");
+            print!("\x1b[H{}", frame);
+            io::stdout().flush().map_err(|error| error.to_string())?;
+        }
+
+        if is_halted {
+            return Ok(true);
+        }
+
+        if let Some(rest) = Duration::from_millis(FRAME_MILLISECONDS).checked_sub(started.elapsed())
+        {
+            thread::sleep(rest);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+pub mod chip;
+pub mod config;
+pub mod cpu;
+pub mod debugger;
+pub mod frontend;
+pub mod os;
+pub mod screen;
+pub mod script;
+pub mod vm;
+
+use crate::vm::Vm;
+use config::{Config, DebuggerConfig};
+use cpu::Cpu;
+use debugger::Debugger;
+use hasm::error::Error;
+use hdl::Circuit;
+use script::{Runner, Target};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process;
+
+// Reads a program either from the binary `.hack` format or assembles it from `.asm`
+pub fn load_program(file: &str) -> Result<Vec<u16>, Vec<Error>> {
+    let source = fs::read_to_string(file).expect("Could not read input file");
+
+    match Path::new(file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("asm") => hasm::assemble(&source),
+        _ => hasm::disasm::parse(&source),
+    }
+}
+
+// Reads a program for the CPU, scripts load `.hack` files which are not assembled yet,
+// so those fall back to the `.asm` source
+pub fn read_program(file: &Path) -> Result<Vec<u16>, String> {
+    let file = if !file.exists() && file.extension() == Some("hack".as_ref()) {
+        file.with_extension("asm")
+    } else {
+        file.to_path_buf()
+    };
+
+    if !file.exists() {
+        return Err(format!("could not read `{}`", file.display()));
+    }
+
+    let name = file.display().to_string();
+    load_program(&name).map_err(|errors| {
+        errors
+            .iter()
+            .map(|error| error.report(&name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+// Picks the emulator for the file a test script loads: VM code and directories of it run on
+// the VM emulator, chips on the hardware simulator and anything else on the CPU
+pub fn load_target(file: Option<&Path>) -> Result<Box<dyn Target>, String> {
+    let file = file.ok_or_else(|| String::from("`load` needs a file or a directory"))?;
+    if file.is_dir() || file.extension() == Some("vm".as_ref()) {
+        return Ok(Box::new(Vm::load(file)?));
+    }
+
+    if file.extension() == Some("hdl".as_ref()) {
+        let circuit = Circuit::load(file).map_err(|error| error.to_string())?;
+        return Ok(Box::new(circuit));
+    }
+
+    let mut cpu = Cpu::new();
+    cpu.load(&read_program(file)?);
+    Ok(Box::new(cpu))
+}
+
+// Runs a `.tst` script, reporting whether its output matched the compare file
+pub fn test(file: &str) -> Result<usize, script::Error> {
+    let source = fs::read_to_string(file).map_err(|_| script::Error {
+        line: 0,
+        message: format!("could not read `{}`", file),
+    })?;
+
+    let statements = script::parse(&source)?;
+    let directory = match Path::new(file).parent() {
+        Some(directory) if directory != Path::new("") => directory,
+        _ => Path::new("."),
+    };
+    // A bare `load` means all the VM files of the script directory
+    let base = directory.to_path_buf();
+    let loader = move |file: Option<&Path>| load_target(Some(file.unwrap_or(&base)));
+
+    Runner::new(directory, &loader).run(&statements)
+}
+
+pub fn run(config: Config) {
+    if config.input_file.ends_with(".tst") {
+        return match test(&config.input_file) {
+            Ok(lines) => println!(
+                "{}: {} output lines compared successfully",
+                config.input_file, lines
+            ),
+            Err(error) => {
+                eprintln!(
+                    "error: {}:{}: {}",
+                    config.input_file, error.line, error.message
+                );
+                process::exit(1);
+            }
+        };
+    }
+
+    let input = Path::new(&config.input_file);
+    if input.is_dir() || input.extension() == Some("vm".as_ref()) {
+        return run_vm(&config);
+    }
+
+    let program = load_program(&config.input_file).unwrap_or_else(|errors| {
+        for error in &errors {
+            eprintln!("{}", error.report(&config.input_file));
+        }
+
+        process::exit(1);
+    });
+
+    let mut cpu = Cpu::new();
+    cpu.load(&program);
+    for (address, value) in &config.ram {
+        cpu.set_ram(*address, *value);
+    }
+
+    let is_halted = match config.display {
+        Some(display) => frontend::run(&mut cpu, display),
+        None => cpu
+            .run_until(config.cycles, Cpu::is_halted)
+            .map_err(|fault| fault.to_string()),
+    }
+    .unwrap_or_else(|error| {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    });
+
+    save_screenshot(&config, cpu.screen());
+
+    println!(
+        "{} after {} cycles: A={} D={} PC={}",
+        if is_halted { "halted" } else { "stopped" },
+        cpu.cycles(),
+        cpu.a as i16,
+        cpu.d as i16,
+        cpu.pc
+    );
+
+    for (from, to) in &config.dump {
+        for address in *from..=*to {
+            println!("RAM[{}] = {}", address, cpu.ram(address) as i16);
+        }
+    }
+}
+
+// Reads debugger commands line by line, an empty line repeats the previous command
+pub fn debug(config: DebuggerConfig) {
+    let mut debugger = Debugger::load(
+        &config.input_file,
+        config.debug_info_file.as_deref(),
+        config.cycles,
+    )
+    .unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let is_interactive = io::stdin().is_terminal();
+    let mut previous = String::new();
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        if is_interactive {
+            print!("(hdbg) ");
+            io::stdout().flush().expect("Could not write to stdout");
+        }
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Could not read from stdin"),
+            None => break,
+        };
+
+        let line = if line.trim().is_empty() {
+            previous.clone()
+        } else {
+            line
+        };
+
+        match line.trim() {
+            "quit" | "q" => break,
+            "" => continue,
+            _ => (),
+        }
+
+        match debugger.execute(&line) {
+            Ok(output) if output.is_empty() => (),
+            Ok(output) => println!("{}", output),
+            Err(error) => println!("error: {}", error),
+        }
+
+        previous = line;
+    }
+}
+
+fn run_vm(config: &Config) {
+    let mut vm = Vm::load(Path::new(&config.input_file)).unwrap_or_else(|error| {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    });
+
+    for (address, value) in &config.ram {
+        vm.set_ram(*address, *value);
+    }
+
+    let result = match config.display {
+        Some(display) => {
+            vm.set_real_time(true);
+            frontend::run(&mut vm, display).map(|_| ())
+        }
+        None => vm.run(config.cycles),
+    };
+
+    result.unwrap_or_else(|error| {
+        let instruction = &vm.program()[vm.pc()];
+        eprintln!(
+            "error: {} in `{}` of {}.vm: {}",
+            instruction.text, instruction.function, instruction.file, error
+        );
+        process::exit(1);
+    });
+
+    save_screenshot(config, vm.screen());
+
+    println!(
+        "{} after {} steps: SP={} LCL={} ARG={} THIS={} THAT={}",
+        if vm.is_halted() { "halted" } else { "stopped" },
+        vm.steps(),
+        vm.ram(crate::vm::SP),
+        vm.ram(crate::vm::LCL),
+        vm.ram(crate::vm::ARG),
+        vm.ram(crate::vm::THIS),
+        vm.ram(crate::vm::THAT)
+    );
+
+    for (from, to) in &config.dump {
+        for address in *from..=*to {
+            println!("RAM[{}] = {}", address, vm.ram(address) as i16);
+        }
+    }
+}
+
+fn save_screenshot(config: &Config, screen: &[u16]) {
+    if let Some(file) = &config.screenshot {
+        screen::save(screen, file).unwrap_or_else(|error| {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        });
+    }
+}
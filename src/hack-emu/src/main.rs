@@ -0,0 +1,6 @@
+use hack_emu::config::Config;
+
+fn main() {
+    let config = Config::from_args();
+    hack_emu::run(config);
+}
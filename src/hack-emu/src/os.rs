@@ -0,0 +1,803 @@
+use crate::vm::Vm;
+use std::collections::HashMap;
+
+// The code of `Sys.init` when the program has none: initializes the OS, runs `Main.main`
+// and halts, just like `Sys.init` of the Jack OS
+pub const SYS_INIT: &str = "
+function Sys.init 0
+call Memory.init 0
+pop temp 0
+call Math.init 0
+pop temp 0
+call Screen.init 0
+pop temp 0
+call Output.init 0
+pop temp 0
+call Keyboard.init 0
+pop temp 0
+call Main.main 0
+pop temp 0
+call Sys.halt 0
+pop temp 0
+label HALT
+goto HALT
+";
+
+const HEAP_BASE: u16 = 2048;
+const HEAP_SIZE: u16 = 14336;
+const SCREEN_WIDTH: i32 = 512;
+const SCREEN_HEIGHT: i32 = 256;
+const ROWS: u16 = 23;
+const COLUMNS: u16 = 64;
+// Steps of the VM taken by a millisecond of `Sys.wait`
+pub const STEPS_PER_MILLISECOND: u64 = 3000;
+const NEW_LINE: u16 = 128;
+const BACKSPACE: u16 = 129;
+
+// A function of the Jack OS implemented by the emulator. It returns `None` while it waits for
+// the keyboard or for time to pass, the VM calls it again on the next step then
+#[derive(Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: u16,
+    function: fn(&mut Vm, &[u16]) -> Result<Option<u16>, String>,
+}
+
+impl std::fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Builtin({})", self.name)
+    }
+}
+
+impl Builtin {
+    pub fn call(&self, vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+        (self.function)(vm, arguments)
+    }
+}
+
+const BUILTINS: &[Builtin] = &[
+    define("Math.init", 0, |_, _| Ok(Some(0))),
+    define("Math.abs", 1, |_, a| {
+        Ok(Some(int(a[0]).wrapping_abs() as u16))
+    }),
+    define("Math.multiply", 2, |_, a| {
+        Ok(Some(int(a[0]).wrapping_mul(int(a[1])) as u16))
+    }),
+    define("Math.divide", 2, math_divide),
+    define("Math.min", 2, |_, a| {
+        Ok(Some(int(a[0]).min(int(a[1])) as u16))
+    }),
+    define("Math.max", 2, |_, a| {
+        Ok(Some(int(a[0]).max(int(a[1])) as u16))
+    }),
+    define("Math.sqrt", 1, math_sqrt),
+    define("Memory.init", 0, memory_init),
+    define("Memory.peek", 1, |vm, a| vm.read(a[0]).map(Some)),
+    define("Memory.poke", 2, |vm, a| {
+        vm.write(a[0], a[1]).map(|_| Some(0))
+    }),
+    define("Memory.alloc", 1, memory_alloc),
+    define("Memory.deAlloc", 1, memory_de_alloc),
+    define("Array.new", 1, array_new),
+    define("Array.dispose", 1, |vm, a| {
+        vm.invoke("Memory.deAlloc", &[a[0]]).map(|_| Some(0))
+    }),
+    define("String.new", 1, string_new),
+    define("String.dispose", 1, string_dispose),
+    define("String.length", 1, |vm, a| vm.read(a[0]).map(Some)),
+    define("String.charAt", 2, string_char_at),
+    define("String.setCharAt", 3, string_set_char_at),
+    define("String.appendChar", 2, string_append_char),
+    define("String.eraseLastChar", 1, string_erase_last_char),
+    define("String.intValue", 1, string_int_value),
+    define("String.setInt", 2, string_set_int),
+    define("String.newLine", 0, |_, _| Ok(Some(NEW_LINE))),
+    define("String.backSpace", 0, |_, _| Ok(Some(BACKSPACE))),
+    define("String.doubleQuote", 0, |_, _| Ok(Some(34))),
+    define("Output.init", 0, output_init),
+    define("Output.moveCursor", 2, output_move_cursor),
+    define("Output.printChar", 1, |vm, a| {
+        print_char(vm, a[0]).map(|_| Some(0))
+    }),
+    define("Output.printString", 1, output_print_string),
+    define("Output.printInt", 1, output_print_int),
+    define("Output.println", 0, |vm, _| {
+        println(vm);
+        Ok(Some(0))
+    }),
+    define("Output.backSpace", 0, |vm, _| {
+        backspace(vm);
+        Ok(Some(0))
+    }),
+    define("Screen.init", 0, |vm, _| {
+        vm.os.color = true;
+        Ok(Some(0))
+    }),
+    define("Screen.clearScreen", 0, |vm, _| {
+        vm.screen_mut().iter_mut().for_each(|word| *word = 0);
+        Ok(Some(0))
+    }),
+    define("Screen.setColor", 1, |vm, a| {
+        vm.os.color = a[0] != 0;
+        Ok(Some(0))
+    }),
+    define("Screen.drawPixel", 2, screen_draw_pixel),
+    define("Screen.drawLine", 4, screen_draw_line),
+    define("Screen.drawRectangle", 4, screen_draw_rectangle),
+    define("Screen.drawCircle", 3, screen_draw_circle),
+    define("Keyboard.init", 0, |vm, _| {
+        vm.os.key = None;
+        vm.os.line = None;
+        Ok(Some(0))
+    }),
+    define("Keyboard.keyPressed", 0, |vm, _| Ok(Some(vm.keyboard()))),
+    define("Keyboard.readChar", 0, |vm, _| read_char(vm)),
+    define("Keyboard.readLine", 1, keyboard_read_line),
+    define("Keyboard.readInt", 1, keyboard_read_int),
+    define("Sys.halt", 0, |vm, _| {
+        vm.halt();
+        Ok(Some(0))
+    }),
+    define("Sys.error", 1, sys_error),
+    define("Sys.wait", 1, sys_wait),
+];
+
+const fn define(
+    name: &'static str,
+    arity: u16,
+    function: fn(&mut Vm, &[u16]) -> Result<Option<u16>, String>,
+) -> Builtin {
+    Builtin {
+        name,
+        arity,
+        function,
+    }
+}
+
+pub fn builtin(name: &str) -> Option<Builtin> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .copied()
+}
+
+// What the builtin OS keeps outside of the RAM
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    // Free blocks of the heap as `(address, size)`, ordered by address
+    free: Vec<(u16, u16)>,
+    blocks: HashMap<u16, u16>,
+    pub(crate) color: bool,
+    // Row and column of the cursor
+    cursor: (u16, u16),
+    // The key `Keyboard.readChar` waits to be released
+    pub(crate) key: Option<u16>,
+    // Characters typed so far into `Keyboard.readLine`
+    pub(crate) line: Option<Vec<u16>>,
+    // The step `Sys.wait` waits for
+    wait: Option<u64>,
+    // Whether `Sys.wait` takes steps, only the display frontend runs the VM at a steady pace
+    pub(crate) real_time: bool,
+}
+
+impl Default for State {
+    fn default() -> State {
+        State {
+            free: vec![(HEAP_BASE, HEAP_SIZE)],
+            blocks: HashMap::new(),
+            color: true,
+            cursor: (0, 0),
+            key: None,
+            line: None,
+            wait: None,
+            real_time: false,
+        }
+    }
+}
+
+fn int(value: u16) -> i16 {
+    value as i16
+}
+
+// Reports the error through `Sys.error` with the codes of the Jack OS
+fn error(vm: &mut Vm, code: u16) -> Result<Option<u16>, String> {
+    vm.invoke("Sys.error", &[code])?;
+    Ok(Some(0))
+}
+
+// Scripts and batch runs do not run in real time, there is nothing to wait for. The terminal
+// frontend waits in steps instead of milliseconds, which keeps games at the same pace
+fn sys_wait(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let duration = int(arguments[0]);
+    if duration < 0 {
+        return error(vm, 1);
+    }
+
+    if !vm.os.real_time {
+        return Ok(Some(0));
+    }
+
+    let steps = vm.steps();
+    let until = *vm
+        .os
+        .wait
+        .get_or_insert(steps + duration as u64 * STEPS_PER_MILLISECOND);
+
+    if steps >= until {
+        vm.os.wait = None;
+        Ok(Some(0))
+    } else {
+        Ok(None)
+    }
+}
+
+fn sys_error(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    for c in "ERR".chars() {
+        vm.invoke("Output.printChar", &[c as u16])?;
+    }
+
+    vm.invoke("Output.printInt", &[arguments[0]])?;
+    vm.halt();
+    Ok(Some(0))
+}
+
+fn math_divide(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    match (int(arguments[0]), int(arguments[1])) {
+        (_, 0) => error(vm, 3),
+        (x, y) => Ok(Some(x.wrapping_div(y) as u16)),
+    }
+}
+
+fn math_sqrt(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let x = int(arguments[0]);
+    if x < 0 {
+        return error(vm, 4);
+    }
+
+    let mut y: i32 = 0;
+    while (y + 1) * (y + 1) <= i32::from(x) {
+        y += 1;
+    }
+
+    Ok(Some(y as u16))
+}
+
+fn memory_init(vm: &mut Vm, _: &[u16]) -> Result<Option<u16>, String> {
+    vm.os.free = vec![(HEAP_BASE, HEAP_SIZE)];
+    vm.os.blocks.clear();
+    Ok(Some(0))
+}
+
+// First fit over the free blocks
+fn memory_alloc(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let size = int(arguments[0]);
+    if size <= 0 {
+        return error(vm, 5);
+    }
+
+    let size = size as u16;
+    let index = match vm.os.free.iter().position(|(_, free)| *free >= size) {
+        Some(index) => index,
+        None => return error(vm, 6),
+    };
+
+    let (address, free) = vm.os.free[index];
+    if free == size {
+        vm.os.free.remove(index);
+    } else {
+        vm.os.free[index] = (address + size, free - size);
+    }
+
+    vm.os.blocks.insert(address, size);
+    Ok(Some(address))
+}
+
+// Returns the block to the free ones, merging it with its free neighbours
+fn memory_de_alloc(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let address = arguments[0];
+    let size = match vm.os.blocks.remove(&address) {
+        Some(size) => size,
+        None => return Ok(Some(0)),
+    };
+
+    let free = &mut vm.os.free;
+    let index = free
+        .iter()
+        .position(|(block, _)| *block > address)
+        .unwrap_or(free.len());
+    free.insert(index, (address, size));
+
+    if index + 1 < free.len() && free[index].0 + free[index].1 == free[index + 1].0 {
+        free[index].1 += free.remove(index + 1).1;
+    }
+
+    if index > 0 && free[index - 1].0 + free[index - 1].1 == free[index].0 {
+        free[index - 1].1 += free.remove(index).1;
+    }
+
+    Ok(Some(0))
+}
+
+fn array_new(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    if int(arguments[0]) <= 0 {
+        return error(vm, 2);
+    }
+
+    vm.invoke("Memory.alloc", &[arguments[0]]).map(Some)
+}
+
+// Strings have the layout of the Jack OS: the length, the capacity and the array of characters
+fn string_new(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let capacity = int(arguments[0]);
+    if capacity < 0 {
+        return error(vm, 14);
+    }
+
+    let capacity = capacity.max(1) as u16;
+    let string = vm.invoke("Memory.alloc", &[3])?;
+    let characters = vm.invoke("Array.new", &[capacity])?;
+
+    vm.write(string, 0)?;
+    vm.write(element(string, 1)?, capacity)?;
+    vm.write(element(string, 2)?, characters)?;
+    Ok(Some(string))
+}
+
+fn string_dispose(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let characters = vm.read(element(arguments[0], 2)?)?;
+    vm.invoke("Array.dispose", &[characters])?;
+    vm.invoke("Memory.deAlloc", &[arguments[0]])?;
+    Ok(Some(0))
+}
+
+fn string_char_at(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let (string, index) = (arguments[0], int(arguments[1]));
+    if index < 0 || index >= int(vm.read(string)?) {
+        return error(vm, 15);
+    }
+
+    let characters = vm.read(element(string, 2)?)?;
+    vm.read(element(characters, index as u16)?).map(Some)
+}
+
+fn string_set_char_at(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let (string, index) = (arguments[0], int(arguments[1]));
+    if index < 0 || index >= int(vm.read(string)?) {
+        return error(vm, 16);
+    }
+
+    let characters = vm.read(element(string, 2)?)?;
+    vm.write(element(characters, index as u16)?, arguments[2])?;
+    Ok(Some(0))
+}
+
+fn string_append_char(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let string = arguments[0];
+    let length = vm.read(string)?;
+    if length >= vm.read(element(string, 1)?)? {
+        return error(vm, 17);
+    }
+
+    let characters = vm.read(element(string, 2)?)?;
+    vm.write(element(characters, length)?, arguments[1])?;
+    vm.write(string, length.wrapping_add(1))?;
+    Ok(Some(string))
+}
+
+fn string_erase_last_char(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let length = vm.read(arguments[0])?;
+    if length == 0 {
+        return error(vm, 18);
+    }
+
+    vm.write(arguments[0], length - 1)?;
+    Ok(Some(0))
+}
+
+fn string_int_value(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let string = arguments[0];
+    let length = vm.read(string)?;
+    let characters = vm.read(element(string, 2)?)?;
+    let text = (0..length)
+        .map(|index| vm.read(element(characters, index)?))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(int_value(&text)))
+}
+
+// Address of a field or an element of a string or an array. The pointer comes from the program,
+// so it may point anywhere
+fn element(pointer: u16, index: u16) -> Result<u16, String> {
+    pointer
+        .checked_add(index)
+        .ok_or_else(|| format!("RAM[{} + {}] is out of memory", pointer, index))
+}
+
+// The leading number of the text, with an optional minus sign
+fn int_value(text: &[u16]) -> u16 {
+    let (is_negative, digits) = match text.first() {
+        Some(45) => (true, &text[1..]),
+        _ => (false, text),
+    };
+
+    let value = digits
+        .iter()
+        .take_while(|c| (48..58).contains(*c))
+        .fold(0u16, |value, c| value.wrapping_mul(10).wrapping_add(c - 48));
+
+    if is_negative {
+        value.wrapping_neg()
+    } else {
+        value
+    }
+}
+
+fn string_set_int(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let string = arguments[0];
+    let text: Vec<u16> = int(arguments[1])
+        .to_string()
+        .bytes()
+        .map(u16::from)
+        .collect();
+    if text.len() > vm.read(element(string, 1)?)? as usize {
+        return error(vm, 19);
+    }
+
+    let characters = vm.read(element(string, 2)?)?;
+    for (index, c) in text.iter().enumerate() {
+        vm.write(element(characters, index as u16)?, *c)?;
+    }
+
+    vm.write(string, text.len() as u16)?;
+    Ok(Some(0))
+}
+
+fn output_init(vm: &mut Vm, _: &[u16]) -> Result<Option<u16>, String> {
+    vm.os.cursor = (0, 0);
+    Ok(Some(0))
+}
+
+fn output_move_cursor(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let (row, column) = (arguments[0], arguments[1]);
+    if row >= ROWS || column >= COLUMNS {
+        return error(vm, 20);
+    }
+
+    vm.os.cursor = (row, column);
+    draw_glyph(vm, &FONT[0]);
+    Ok(Some(0))
+}
+
+fn output_print_string(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let length = vm.invoke("String.length", &[arguments[0]])?;
+    for index in 0..length {
+        let c = vm.invoke("String.charAt", &[arguments[0], index])?;
+        print_char(vm, c)?;
+    }
+
+    Ok(Some(0))
+}
+
+fn output_print_int(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    for c in int(arguments[0]).to_string().bytes() {
+        print_char(vm, u16::from(c))?;
+    }
+
+    Ok(Some(0))
+}
+
+fn print_char(vm: &mut Vm, c: u16) -> Result<(), String> {
+    match c {
+        NEW_LINE => println(vm),
+        BACKSPACE => backspace(vm),
+        32..=126 => draw_glyph(vm, &FONT[c as usize - 32]),
+        _ => draw_glyph(vm, &BLACK_SQUARE),
+    }
+
+    if c != NEW_LINE && c != BACKSPACE {
+        let (row, column) = vm.os.cursor;
+        vm.os.cursor = if column + 1 < COLUMNS {
+            (row, column + 1)
+        } else {
+            ((row + 1) % ROWS, 0)
+        };
+    }
+
+    Ok(())
+}
+
+fn println(vm: &mut Vm) {
+    vm.os.cursor = ((vm.os.cursor.0 + 1) % ROWS, 0);
+}
+
+fn backspace(vm: &mut Vm) {
+    let (row, column) = vm.os.cursor;
+    vm.os.cursor = match (row, column) {
+        (0, 0) => (ROWS - 1, COLUMNS - 1),
+        (row, 0) => (row - 1, COLUMNS - 1),
+        (row, column) => (row, column - 1),
+    };
+
+    draw_glyph(vm, &FONT[0]);
+}
+
+// Characters are 8 pixels wide, so every one of them is a byte of a screen word
+fn draw_glyph(vm: &mut Vm, glyph: &[u8; 11]) {
+    let (row, column) = vm.os.cursor;
+    let shift = (column % 2) * 8;
+
+    for (dy, bits) in glyph.iter().enumerate() {
+        let index = (row as usize * 11 + dy) * 32 + column as usize / 2;
+        let word = &mut vm.screen_mut()[index];
+        *word = (*word & !(0xFF << shift)) | (u16::from(*bits) << shift);
+    }
+}
+
+fn set_pixel(vm: &mut Vm, x: i32, y: i32) {
+    if !on_screen(x, y) {
+        return;
+    }
+
+    let color = vm.os.color;
+    let word = &mut vm.screen_mut()[(y * 32 + x / 16) as usize];
+    if color {
+        *word |= 1 << (x % 16);
+    } else {
+        *word &= !(1 << (x % 16));
+    }
+}
+
+fn on_screen(x: i32, y: i32) -> bool {
+    (0..SCREEN_WIDTH).contains(&x) && (0..SCREEN_HEIGHT).contains(&y)
+}
+
+fn screen_draw_pixel(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let (x, y) = (i32::from(int(arguments[0])), i32::from(int(arguments[1])));
+    if !on_screen(x, y) {
+        return error(vm, 7);
+    }
+
+    set_pixel(vm, x, y);
+    Ok(Some(0))
+}
+
+fn screen_draw_line(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let [x1, y1, x2, y2] = coordinates(arguments);
+    if !on_screen(x1, y1) || !on_screen(x2, y2) {
+        return error(vm, 8);
+    }
+
+    // Bresenham's algorithm
+    let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+    let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+    let (mut x, mut y, mut balance) = (x1, y1, dx + dy);
+
+    loop {
+        set_pixel(vm, x, y);
+        if x == x2 && y == y2 {
+            break;
+        }
+
+        let doubled = 2 * balance;
+        if doubled >= dy {
+            balance += dy;
+            x += sx;
+        }
+
+        if doubled <= dx {
+            balance += dx;
+            y += sy;
+        }
+    }
+
+    Ok(Some(0))
+}
+
+fn screen_draw_rectangle(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let [x1, y1, x2, y2] = coordinates(arguments);
+    if !on_screen(x1, y1) || !on_screen(x2, y2) || x1 > x2 || y1 > y2 {
+        return error(vm, 9);
+    }
+
+    for y in y1..=y2 {
+        for x in x1..=x2 {
+            set_pixel(vm, x, y);
+        }
+    }
+
+    Ok(Some(0))
+}
+
+// Filled, the part of the circle off the screen is cut
+fn screen_draw_circle(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let (x, y, r) = (
+        i32::from(int(arguments[0])),
+        i32::from(int(arguments[1])),
+        i32::from(int(arguments[2])),
+    );
+
+    if !on_screen(x, y) {
+        return error(vm, 12);
+    }
+
+    if !(0..=181).contains(&r) {
+        return error(vm, 13);
+    }
+
+    for dy in -r..=r {
+        let dx = f64::from(r * r - dy * dy).sqrt() as i32;
+        for px in x - dx..=x + dx {
+            set_pixel(vm, px, y + dy);
+        }
+    }
+
+    Ok(Some(0))
+}
+
+fn coordinates(arguments: &[u16]) -> [i32; 4] {
+    let mut coordinates = [0; 4];
+    for (coordinate, argument) in coordinates.iter_mut().zip(arguments) {
+        *coordinate = i32::from(int(*argument));
+    }
+
+    coordinates
+}
+
+// Waits for a key to be pressed and released, then echoes it
+fn read_char(vm: &mut Vm) -> Result<Option<u16>, String> {
+    let key = vm.keyboard();
+
+    match vm.os.key {
+        None if key != 0 => vm.os.key = Some(key),
+        Some(pressed) if key != pressed => {
+            vm.os.key = None;
+            vm.invoke("Output.printChar", &[pressed])?;
+            return Ok(Some(pressed));
+        }
+        _ => (),
+    }
+
+    Ok(None)
+}
+
+// Collects the characters typed until a new line, handling backspaces
+fn read_line(vm: &mut Vm, message: u16) -> Result<Option<Vec<u16>>, String> {
+    if vm.os.line.is_none() {
+        vm.invoke("Output.printString", &[message])?;
+        vm.os.line = Some(Vec::new());
+    }
+
+    match read_char(vm)? {
+        Some(NEW_LINE) => Ok(vm.os.line.take()),
+        Some(BACKSPACE) => {
+            vm.os.line.as_mut().unwrap().pop();
+            Ok(None)
+        }
+        Some(c) => {
+            vm.os.line.as_mut().unwrap().push(c);
+            Ok(None)
+        }
+        None => Ok(None),
+    }
+}
+
+fn keyboard_read_line(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    let text = match read_line(vm, arguments[0])? {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+
+    let string = vm.invoke("String.new", &[text.len() as u16])?;
+    for c in text {
+        vm.invoke("String.appendChar", &[string, c])?;
+    }
+
+    Ok(Some(string))
+}
+
+fn keyboard_read_int(vm: &mut Vm, arguments: &[u16]) -> Result<Option<u16>, String> {
+    Ok(read_line(vm, arguments[0])?.map(|text| int_value(&text)))
+}
+
+// Glyphs of the characters 32..=126, 11 rows of 8 pixels each, the least significant bit is
+// the leftmost pixel. The same font the Jack OS builds in `Output.initMap`
+const FONT: [[u8; 11]; 95] = [
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],           // space
+    [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0],   // !
+    [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0],        // "
+    [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0],   // #
+    [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0],  // $
+    [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0],     // %
+    [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0],  // &
+    [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0],         // '
+    [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0],       // (
+    [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0],    // )
+    [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0],      // *
+    [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0],      // +
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0],         // ,
+    [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0],          // -
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0],         // .
+    [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0],       // /
+    [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0],  // 0
+    [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0],  // 1
+    [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0],    // 2
+    [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0],  // 3
+    [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0],  // 4
+    [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0],    // 5
+    [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0],     // 6
+    [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0],  // 7
+    [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0],  // 8
+    [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0],  // 9
+    [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0],       // :
+    [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0],       // ;
+    [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0],       // <
+    [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0],         // =
+    [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0],        // >
+    [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0],   // ?
+    [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0],   // @
+    [12, 30, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // A
+    [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0],  // B
+    [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0],     // C
+    [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0],  // D
+    [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0],  // E
+    [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0],     // F
+    [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0],   // G
+    [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // H
+    [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // I
+    [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0],  // J
+    [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0],  // K
+    [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0],        // L
+    [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0],  // M
+    [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0],  // N
+    [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // O
+    [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0],      // P
+    [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0], // Q
+    [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0],  // R
+    [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0],   // S
+    [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0],  // T
+    [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // U
+    [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0],  // V
+    [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0],  // W
+    [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0],  // X
+    [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0],  // Y
+    [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0],   // Z
+    [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0],         // [
+    [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0],       // \
+    [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0],  // ]
+    [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0],         // ^
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0],          // _
+    [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0],         // `
+    [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0],     // a
+    [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0],     // b
+    [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0],       // c
+    [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0],  // d
+    [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0],      // e
+    [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0],      // f
+    [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0],   // g
+    [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0],     // h
+    [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0],   // i
+    [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0],  // j
+    [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0],     // k
+    [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // l
+    [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0],     // m
+    [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0],     // n
+    [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0],     // o
+    [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0],      // p
+    [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0],    // q
+    [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0],        // r
+    [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0],      // s
+    [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0],        // t
+    [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0],     // u
+    [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0],     // v
+    [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0],     // w
+    [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0],     // x
+    [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0],    // y
+    [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0],      // z
+    [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0],   // {
+    [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0],  // |
+    [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0],    // }
+    [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0],        // ~
+];
+
+// Drawn for characters outside of the font
+const BLACK_SQUARE: [u8; 11] = [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0];
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::Path;
+
+pub const WIDTH: usize = 512;
+pub const HEIGHT: usize = 256;
+const WORDS_PER_ROW: usize = WIDTH / 16;
+
+// How the screen is drawn in a terminal: braille characters pack 2x4 pixels into a cell,
+// half blocks pack 1x2 pixels and need a terminal 512 columns wide
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Display {
+    Braille,
+    Blocks,
+}
+
+impl Display {
+    pub const NAMES: [&'static str; 2] = ["braille", "blocks"];
+
+    pub fn from_name(name: &str) -> Option<Display> {
+        match name {
+            "braille" => Some(Display::Braille),
+            "blocks" => Some(Display::Blocks),
+            _ => None,
+        }
+    }
+
+    // Lines of the picture, black pixels are drawn and white ones are left blank
+    pub fn render(self, screen: &[u16]) -> Vec<String> {
+        match self {
+            Display::Braille => braille(screen),
+            Display::Blocks => blocks(screen),
+        }
+    }
+}
+
+// The least significant bit of a word is its leftmost pixel
+pub fn pixel(screen: &[u16], x: usize, y: usize) -> bool {
+    screen[y * WORDS_PER_ROW + x / 16] >> (x % 16) & 1 == 1
+}
+
+fn braille(screen: &[u16]) -> Vec<String> {
+    // Dots of a braille character by their column and row in the cell
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    (0..HEIGHT)
+        .step_by(4)
+        .map(|top| {
+            (0..WIDTH)
+                .step_by(2)
+                .map(|left| {
+                    let mut dots = 0;
+                    for (column, rows) in DOTS.iter().enumerate() {
+                        for (row, dot) in rows.iter().enumerate() {
+                            if pixel(screen, left + column, top + row) {
+                                dots |= dot;
+                            }
+                        }
+                    }
+
+                    std::char::from_u32(0x2800 + dots).unwrap_or(' ')
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn blocks(screen: &[u16]) -> Vec<String> {
+    (0..HEIGHT)
+        .step_by(2)
+        .map(|top| {
+            (0..WIDTH)
+                .map(
+                    |x| match (pixel(screen, x, top), pixel(screen, x, top + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+// Writes the screen as `.png` or `.pbm`, depending on the extension of the file
+pub fn save(screen: &[u16], file: &str) -> Result<(), String> {
+    let image = match Path::new(file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("png") => png(screen),
+        Some("pbm") => pbm(screen),
+        _ => return Err(format!("`{}` must be a .png or a .pbm file", file)),
+    };
+
+    fs::write(file, image).map_err(|_| format!("could not write `{}`", file))
+}
+
+// Packs the rows of pixels into bytes, the leftmost pixel is the most significant bit
+fn rows(screen: &[u16], is_black: bool) -> impl Iterator<Item = Vec<u8>> + '_ {
+    (0..HEIGHT).map(move |y| {
+        (0..WIDTH / 8)
+            .map(|byte| {
+                (0..8).fold(0, |bits, bit| {
+                    let is_set = pixel(screen, byte * 8 + bit, y) == is_black;
+                    bits << 1 | is_set as u8
+                })
+            })
+            .collect()
+    })
+}
+
+// Binary portable bitmap where 1 is black
+pub fn pbm(screen: &[u16]) -> Vec<u8> {
+    let mut image = format!("P4\n{} {}\n", WIDTH, HEIGHT).into_bytes();
+    for row in rows(screen, true) {
+        image.extend(row);
+    }
+
+    image
+}
+
+// 1-bit grayscale PNG where 1 is white, the pixels are stored without compression
+pub fn png(screen: &[u16]) -> Vec<u8> {
+    let mut pixels: Vec<u8> = Vec::new();
+    for row in rows(screen, false) {
+        // Each scanline starts with its filter type, none
+        pixels.push(0);
+        pixels.extend(row);
+    }
+
+    // A zlib stream of stored deflate blocks, which are at most 65535 bytes long
+    let mut data = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = pixels.chunks(0xFFFF).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        let length = block.len() as u16;
+        data.push((index + 1 == blocks.len()) as u8);
+        data.extend(&length.to_le_bytes());
+        data.extend(&(!length).to_le_bytes());
+        data.extend(*block);
+    }
+    data.extend(&adler32(&pixels).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend(&(WIDTH as u32).to_be_bytes());
+    header.extend(&(HEIGHT as u32).to_be_bytes());
+    // Bit depth 1, grayscale, deflate, no filtering method and no interlacing
+    header.extend(&[1, 0, 0, 0, 0]);
+
+    let mut image = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    chunk(&mut image, b"IHDR", &header);
+    chunk(&mut image, b"IDAT", &data);
+    chunk(&mut image, b"IEND", &[]);
+    image
+}
+
+fn chunk(image: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    image.extend(&(data.len() as u32).to_be_bytes());
+    image.extend(kind);
+    image.extend(data);
+
+    let checked: Vec<u8> = kind.iter().chain(data).copied().collect();
+    image.extend(&crc32(&checked).to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    b << 16 | a
+}
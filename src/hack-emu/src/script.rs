@@ -0,0 +1,548 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Whatever a test script drives: the CPU emulator, the VM emulator or a chip
+pub trait Target {
+    fn get(&self, name: &str) -> Result<i32, String>;
+    fn set(&mut self, name: &str, value: i32) -> Result<(), String>;
+    // Simulation commands like `ticktock`, `vmstep`, `eval` or `ROM32K load Add.hack`,
+    // files they mention are relative to the script directory
+    fn execute(&mut self, command: &[String], directory: &Path) -> Result<(), String>;
+}
+
+// Creates the target for `load <file>`, or for a bare `load` which means the script directory
+pub type Loader = dyn Fn(Option<&Path>) -> Result<Box<dyn Target>, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Radix {
+    Binary,
+    Decimal,
+    Hexadecimal,
+    String,
+}
+
+// `name%F<left>.<width>.<right>` from the `output-list` command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub radix: Radix,
+    pub left: usize,
+    pub width: usize,
+    pub right: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Load(Option<String>),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<Column>),
+    Set(String, i32),
+    Output,
+    Echo(String),
+    ClearEcho,
+    Repeat(Option<usize>, Vec<Statement>),
+    While(String, Comparison, i32, Vec<Statement>),
+    Simulate(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub line: usize,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Separator,
+    Open,
+    Close,
+}
+
+pub fn parse(source: &str) -> Result<Vec<Statement>, Error> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, index: 0 };
+    let statements = parser.block()?;
+
+    match parser.tokens.get(parser.index) {
+        Some((line, _)) => Err(error(*line, "unexpected `}`")),
+        None => Ok(statements),
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error {
+        line,
+        message: String::from(message),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut line = 1;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '/' if next == Some('/') => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            '/' if next == Some('*') => {
+                index += 2;
+                while index < chars.len()
+                    && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+                {
+                    if chars[index] == '\n' {
+                        line += 1;
+                    }
+                    index += 1;
+                }
+                index += 1;
+            }
+            ',' | ';' => tokens.push((line, Token::Separator)),
+            '{' => tokens.push((line, Token::Open)),
+            '}' => tokens.push((line, Token::Close)),
+            '"' => {
+                let start = index + 1;
+                index = start;
+                while index < chars.len() && chars[index] != '"' {
+                    index += 1;
+                }
+
+                if index == chars.len() {
+                    return Err(error(line, "unterminated string"));
+                }
+
+                tokens.push((line, Token::Text(chars[start..index].iter().collect())));
+            }
+            _ => {
+                let start = index;
+                while index < chars.len()
+                    && !chars[index].is_whitespace()
+                    && !",;{}\"".contains(chars[index])
+                {
+                    index += 1;
+                }
+
+                tokens.push((line, Token::Word(chars[start..index].iter().collect())));
+                continue;
+            }
+        }
+
+        index += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+}
+
+impl Parser {
+    fn block(&mut self) -> Result<Vec<Statement>, Error> {
+        let mut statements: Vec<Statement> = Vec::new();
+
+        while let Some((line, token)) = self.tokens.get(self.index).cloned() {
+            match token {
+                Token::Close => break,
+                Token::Separator => self.index += 1,
+                _ => statements.push(Statement {
+                    line,
+                    command: self.command(line)?,
+                }),
+            }
+        }
+
+        Ok(statements)
+    }
+
+    fn command(&mut self, line: usize) -> Result<Command, Error> {
+        let mut words: Vec<String> = Vec::new();
+        let mut text: Option<String> = None;
+
+        loop {
+            match self.tokens.get(self.index).cloned() {
+                Some((_, Token::Word(word))) => words.push(word),
+                Some((_, Token::Text(string))) => text = Some(string),
+                Some((_, Token::Open)) => {
+                    self.index += 1;
+                    let body = self.block()?;
+                    if self.tokens.get(self.index).map(|(_, token)| token) != Some(&Token::Close) {
+                        return Err(error(line, "missing `}`"));
+                    }
+
+                    self.index += 1;
+                    return loop_command(line, &words, body);
+                }
+                Some((_, Token::Separator)) | Some((_, Token::Close)) | None => break,
+            }
+
+            self.index += 1;
+        }
+
+        if words.is_empty() {
+            return Err(error(line, "expected a command"));
+        }
+
+        let arguments: Vec<&str> = words.iter().skip(1).map(String::as_str).collect();
+        let command = match (words[0].as_str(), arguments.as_slice()) {
+            ("load", []) => Command::Load(None),
+            ("load", [file]) => Command::Load(Some(String::from(*file))),
+            ("output-file", [file]) => Command::OutputFile(String::from(*file)),
+            ("compare-to", [file]) => Command::CompareTo(String::from(*file)),
+            ("output-list", columns) => Command::OutputList(
+                columns
+                    .iter()
+                    .map(|column| {
+                        parse_column(column).ok_or_else(|| {
+                            error(line, &format!("invalid output column `{}`", column))
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            ("set", [name, value]) => Command::Set(
+                String::from(*name),
+                parse_value(value)
+                    .ok_or_else(|| error(line, &format!("invalid value `{}`", value)))?,
+            ),
+            ("output", []) => Command::Output,
+            ("echo", []) => Command::Echo(text.unwrap_or_default()),
+            ("clear-echo", []) => Command::ClearEcho,
+            ("repeat", _) | ("while", _) => {
+                return Err(error(line, &format!("`{}` is missing its body", words[0])))
+            }
+            _ => Command::Simulate(words),
+        };
+
+        Ok(command)
+    }
+}
+
+fn loop_command(line: usize, words: &[String], body: Vec<Statement>) -> Result<Command, Error> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+    match words.as_slice() {
+        ["repeat"] => Ok(Command::Repeat(None, body)),
+        ["repeat", count] => match count.parse() {
+            Ok(count) => Ok(Command::Repeat(Some(count), body)),
+            Err(_) => Err(error(line, &format!("invalid repeat count `{}`", count))),
+        },
+        ["while", name, comparison, value] => {
+            let comparison = match *comparison {
+                "=" => Comparison::Equal,
+                "<>" => Comparison::NotEqual,
+                "<" => Comparison::Less,
+                "<=" => Comparison::LessOrEqual,
+                ">" => Comparison::Greater,
+                ">=" => Comparison::GreaterOrEqual,
+                _ => return Err(error(line, &format!("unknown comparison `{}`", comparison))),
+            };
+
+            match parse_value(value) {
+                Some(value) => Ok(Command::While(String::from(*name), comparison, value, body)),
+                None => Err(error(line, &format!("invalid value `{}`", value))),
+            }
+        }
+        _ => Err(error(line, "only `repeat` and `while` may have a body")),
+    }
+}
+
+// Decimal by default, `%B`, `%X` and `%D` prefixes pick the radix explicitly
+pub fn parse_value(value: &str) -> Option<i32> {
+    let (radix, digits) = match value.get(..2) {
+        Some("%B") => (2, &value[2..]),
+        Some("%X") => (16, &value[2..]),
+        Some("%D") => (10, &value[2..]),
+        _ => (10, value),
+    };
+
+    i32::from_str_radix(digits, radix).ok()
+}
+
+fn parse_column(column: &str) -> Option<Column> {
+    let (name, format) = match column.find('%') {
+        Some(index) => (&column[..index], &column[index + 1..]),
+        None => (column, "D1.6.1"),
+    };
+
+    let radix = match format.get(..1)? {
+        "B" => Radix::Binary,
+        "D" => Radix::Decimal,
+        "X" => Radix::Hexadecimal,
+        "S" => Radix::String,
+        _ => return None,
+    };
+
+    let sizes: Vec<usize> = format[1..]
+        .split('.')
+        .map(|size| size.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match sizes.as_slice() {
+        [left, width, right] => Some(Column {
+            name: String::from(name),
+            radix,
+            left: *left,
+            width: *width,
+            right: *right,
+        }),
+        _ => None,
+    }
+}
+
+impl Column {
+    // Names are centered over the column and cut when they do not fit
+    pub fn header(&self) -> String {
+        let space = self.left + self.width + self.right;
+        let name: String = self.name.chars().take(space).collect();
+        let left = (space - name.chars().count()) / 2;
+        let right = space - left - name.chars().count();
+
+        format!("{}{}{}", " ".repeat(left), name, " ".repeat(right))
+    }
+
+    pub fn value(&self, value: &str) -> String {
+        let value = match (self.radix, value.parse::<i32>()) {
+            (Radix::Binary, Ok(number)) => format!(
+                "{:0width$b}",
+                number as u32 & mask(self.width),
+                width = self.width
+            ),
+            (Radix::Hexadecimal, Ok(number)) => format!(
+                "{:0width$X}",
+                number as u32 & mask(self.width * 4),
+                width = self.width
+            ),
+            (Radix::String, _) | (_, Err(_)) => format!("{:<width$}", value, width = self.width),
+            (Radix::Decimal, Ok(number)) => format!("{:>width$}", number, width = self.width),
+        };
+
+        format!(
+            "{}{}{}",
+            " ".repeat(self.left),
+            value,
+            " ".repeat(self.right)
+        )
+    }
+}
+
+fn mask(bits: usize) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+// Executes scripts against the loaded target, writing and comparing their output
+pub struct Runner<'a> {
+    directory: PathBuf,
+    loader: &'a Loader,
+    target: Option<Box<dyn Target>>,
+    columns: Vec<Column>,
+    output: Vec<String>,
+    output_file: Option<PathBuf>,
+    compare: Option<Vec<String>>,
+    // Clock of chips: the number of full cycles and whether the clock is up after `tick`
+    time: (u64, bool),
+}
+
+impl<'a> Runner<'a> {
+    pub fn new(directory: &Path, loader: &'a Loader) -> Runner<'a> {
+        Runner {
+            directory: directory.to_path_buf(),
+            loader,
+            target: None,
+            columns: Vec::new(),
+            output: Vec::new(),
+            output_file: None,
+            compare: None,
+            time: (0, false),
+        }
+    }
+
+    // Number of output lines matching the compare file
+    pub fn run(&mut self, statements: &[Statement]) -> Result<usize, Error> {
+        let result = self.block(statements);
+
+        if let Some(file) = &self.output_file {
+            let mut contents = self.output.join("\n");
+            contents.push('\n');
+            fs::write(file, contents)
+                .map_err(|_| error(0, &format!("could not write `{}`", file.display())))?;
+        }
+
+        result.map(|_| self.output.len())
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        for statement in statements {
+            let line = statement.line;
+            let at = |message| Error { line, message };
+
+            match &statement.command {
+                Command::Repeat(Some(count), body) => {
+                    for _ in 0..*count {
+                        self.block(body)?;
+                    }
+                }
+                Command::While(name, comparison, value, body) => {
+                    while compare(self.get_number(name).map_err(at)?, *comparison, *value) {
+                        self.block(body)?;
+                    }
+                }
+                command => self.execute(command).map_err(at)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Load(file) => {
+                let path = file.as_ref().map(|file| self.directory.join(file));
+                self.target = Some((self.loader)(path.as_deref())?);
+                self.time = (0, false);
+            }
+            Command::OutputFile(file) => self.output_file = Some(self.directory.join(file)),
+            Command::CompareTo(file) => {
+                let path = self.directory.join(file);
+                let contents = fs::read_to_string(&path)
+                    .map_err(|_| format!("could not read `{}`", path.display()))?;
+                self.compare = Some(contents.lines().map(String::from).collect());
+            }
+            Command::OutputList(columns) => {
+                self.columns = columns.clone();
+                let header: Vec<String> = self.columns.iter().map(Column::header).collect();
+                self.emit(format!("|{}|", header.join("|")))?;
+            }
+            Command::Set(name, value) => self.target()?.set(name, *value)?,
+            Command::Output => {
+                let mut values: Vec<String> = Vec::new();
+                for column in self.columns.clone() {
+                    values.push(column.value(&self.get(&column.name)?));
+                }
+
+                self.emit(format!("|{}|", values.join("|")))?;
+            }
+            Command::Echo(message) => println!("{}", message),
+            Command::ClearEcho => (),
+            Command::Repeat(None, _) => {
+                return Err(String::from("`repeat` without a count never ends"))
+            }
+            Command::Repeat(Some(_), _) | Command::While(..) => unreachable!(),
+            Command::Simulate(command) => {
+                match command[0].as_str() {
+                    "tick" => self.time.1 = true,
+                    "tock" | "ticktock" => self.time = (self.time.0 + 1, false),
+                    _ => (),
+                }
+
+                let directory = self.directory.clone();
+                self.target()?.execute(command, &directory)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn target(&mut self) -> Result<&mut Box<dyn Target>, String> {
+        self.target
+            .as_mut()
+            .ok_or_else(|| String::from("nothing is loaded yet"))
+    }
+
+    fn get_number(&mut self, name: &str) -> Result<i32, String> {
+        self.target()?.get(name)
+    }
+
+    fn get(&mut self, name: &str) -> Result<String, String> {
+        if name == "time" {
+            return Ok(format!(
+                "{}{}",
+                self.time.0,
+                if self.time.1 { "+" } else { "" }
+            ));
+        }
+
+        self.target()?.get(name).map(|value| value.to_string())
+    }
+
+    // Appends a line to the output and checks it against the same line of the compare file,
+    // where `*` matches any character
+    fn emit(&mut self, line: String) -> Result<(), String> {
+        let number = self.output.len() + 1;
+        let expected = self
+            .compare
+            .as_ref()
+            .map(|compare| compare.get(number - 1).cloned().unwrap_or_default());
+        self.output.push(line);
+
+        match expected {
+            Some(expected) if !matches(&expected, &self.output[number - 1]) => Err(format!(
+                "comparison failure at line {}\nexpected: {}\n   found: {}",
+                number,
+                expected.trim_end(),
+                self.output[number - 1]
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn matches(expected: &str, line: &str) -> bool {
+    let expected: Vec<char> = expected.trim_end().chars().collect();
+    let line: Vec<char> = line.trim_end().chars().collect();
+
+    expected.len() == line.len() && expected.iter().zip(&line).all(|(e, c)| *e == '*' || e == c)
+}
+
+fn compare(lhs: i32, comparison: Comparison, rhs: i32) -> bool {
+    match comparison {
+        Comparison::Equal => lhs == rhs,
+        Comparison::NotEqual => lhs != rhs,
+        Comparison::Less => lhs < rhs,
+        Comparison::LessOrEqual => lhs <= rhs,
+        Comparison::Greater => lhs > rhs,
+        Comparison::GreaterOrEqual => lhs >= rhs,
+    }
+}
@@ -0,0 +1,649 @@
+use crate::cpu::{indexed, KBD, RAM_SIZE, SCREEN};
+use crate::os::{self, Builtin};
+use crate::script::Target;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use vm::opcode::{self, OpCode};
+
+pub const SP: u16 = 0;
+pub const LCL: u16 = 1;
+pub const ARG: u16 = 2;
+pub const THIS: u16 = 3;
+pub const THAT: u16 = 4;
+pub const TEMP: u16 = 5;
+pub const STATIC: u16 = 16;
+pub const STACK: u16 = 256;
+
+// Return address of calls made by the emulator itself: the bootstrap call of `Sys.init`
+// and calls of VM functions from the builtin OS. It is past the end of any program
+const RETURN_TO_EMULATOR: usize = 0xFFFF;
+
+// A builtin function calling back into the VM code gives up after this many steps
+const MAX_NESTED_STEPS: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Constant,
+    Local,
+    Argument,
+    This,
+    That,
+    Pointer,
+    Temp,
+    // Statics of a file start at their own base address
+    Static(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Callee {
+    Function(usize),
+    Builtin(Builtin),
+}
+
+// VM commands with segments, labels and functions already resolved
+#[derive(Debug, Clone, Copy)]
+pub enum Command {
+    Arithmetic(Operation),
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Label,
+    Goto(usize),
+    IfGoto(usize),
+    Function(u16),
+    Call(Callee, u16),
+    Return,
+}
+
+// A command with the function and the VM file it comes from
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub command: Command,
+    pub text: String,
+    pub function: String,
+    pub file: String,
+}
+
+// The VM emulator, every `step` executes one VM command. Functions missing from the program
+// are taken from the builtin Jack OS
+pub struct Vm {
+    ram: Vec<u16>,
+    program: Vec<Instruction>,
+    functions: HashMap<String, usize>,
+    pc: usize,
+    halted: bool,
+    steps: u64,
+    pub(crate) os: os::State,
+}
+
+impl Vm {
+    // Loads a `.vm` file or all `.vm` files of a directory. The program starts from `Sys.init`
+    // when it has one, a directory without it starts from the builtin `Sys.init`
+    pub fn load(path: &Path) -> Result<Vm, String> {
+        let mut files: Vec<_> = if path.is_dir() {
+            fs::read_dir(path)
+                .map_err(|_| format!("could not read `{}`", path.display()))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && path.extension() == Some("vm".as_ref()))
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        files.sort();
+
+        let mut sources: Vec<(String, String)> = Vec::new();
+        for file in &files {
+            let source = fs::read_to_string(file)
+                .map_err(|_| format!("could not read `{}`", file.display()))?;
+            let name = file.file_stem().unwrap().to_string_lossy().to_string();
+            sources.push((name, source));
+        }
+
+        let sources: Vec<(&str, &str)> = sources
+            .iter()
+            .map(|(name, source)| (name.as_str(), source.as_str()))
+            .collect();
+
+        Vm::new(&sources, path.is_dir())
+    }
+
+    // Links VM files given as `(name, source)`, the name picks the statics of the file
+    pub fn new(sources: &[(&str, &str)], bootstrap: bool) -> Result<Vm, String> {
+        let mut files: Vec<(&str, Vec<OpCode>)> = Vec::new();
+        for (name, source) in sources {
+            files.push((name, parse(name, source)?));
+        }
+
+        let defines = |files: &[(&str, Vec<OpCode>)], name: &str| {
+            files.iter().any(|(_, opcodes)| {
+                opcodes.iter().any(
+                    |opcode| matches!(opcode, OpCode::Function(function) if function.id == name),
+                )
+            })
+        };
+
+        let has_sys_init = defines(&files, "Sys.init");
+        if !has_sys_init && bootstrap {
+            files.push(("Sys", parse("Sys", os::SYS_INIT)?));
+        }
+
+        let mut vm = Vm {
+            ram: vec![0; RAM_SIZE],
+            program: Vec::new(),
+            functions: HashMap::new(),
+            pc: 0,
+            halted: false,
+            steps: 0,
+            os: os::State::default(),
+        };
+
+        vm.link(&files)?;
+        vm.ram[SP as usize] = STACK;
+
+        if has_sys_init || bootstrap {
+            // The same frame the bootstrap code of the VM translator leaves on the stack
+            vm.push_frame(RETURN_TO_EMULATOR as u16)?;
+            vm.ram[ARG as usize] = STACK;
+            vm.ram[LCL as usize] = vm.ram[SP as usize];
+            vm.pc = vm.functions["Sys.init"];
+        }
+
+        Ok(vm)
+    }
+
+    fn link(&mut self, files: &[(&str, Vec<OpCode>)]) -> Result<(), String> {
+        let mut labels: HashMap<(String, &str), usize> = HashMap::new();
+        let mut ip = 0;
+
+        for (file, opcodes) in files {
+            let mut function = String::new();
+            for opcode in opcodes {
+                match opcode {
+                    OpCode::Function(opcode) => {
+                        function = String::from(opcode.id);
+                        if self.functions.insert(function.clone(), ip).is_some() {
+                            return Err(format!("function `{}` is defined twice", function));
+                        }
+                    }
+                    OpCode::Label(opcode) => {
+                        labels.insert((format!("{}.{}", file, function), opcode.id), ip);
+                    }
+                    _ => (),
+                }
+
+                ip += 1;
+            }
+        }
+
+        let mut static_base = STATIC;
+        for (file, opcodes) in files {
+            let mut function = String::new();
+            let mut statics = 0;
+
+            for opcode in opcodes {
+                if let OpCode::Function(opcode) = opcode {
+                    function = String::from(opcode.id);
+                }
+
+                let label = |id: &str| {
+                    labels
+                        .get(&(format!("{}.{}", file, function), id))
+                        .copied()
+                        .ok_or_else(|| format!("label `{}` is not defined in `{}`", id, function))
+                };
+
+                let command = match opcode {
+                    OpCode::Add => Command::Arithmetic(Operation::Add),
+                    OpCode::Sub => Command::Arithmetic(Operation::Sub),
+                    OpCode::Neg => Command::Arithmetic(Operation::Neg),
+                    OpCode::Eq => Command::Arithmetic(Operation::Eq),
+                    OpCode::Gt => Command::Arithmetic(Operation::Gt),
+                    OpCode::Lt => Command::Arithmetic(Operation::Lt),
+                    OpCode::And => Command::Arithmetic(Operation::And),
+                    OpCode::Or => Command::Arithmetic(Operation::Or),
+                    OpCode::Not => Command::Arithmetic(Operation::Not),
+                    OpCode::Return => Command::Return,
+                    OpCode::Push(opcode) => {
+                        let segment = segment(opcode.segment, opcode.i, static_base)?;
+                        if let Segment::Static(_) = segment {
+                            statics = statics.max(opcode.i + 1);
+                        }
+
+                        Command::Push(segment, opcode.i)
+                    }
+                    OpCode::Pop(opcode) => {
+                        let segment = segment(opcode.segment, opcode.i, static_base)?;
+                        if let Segment::Static(_) = segment {
+                            statics = statics.max(opcode.i + 1);
+                        }
+
+                        Command::Pop(segment, opcode.i)
+                    }
+                    OpCode::Move(_) => unreachable!("the parser does not make moves"),
+                    OpCode::Label(_) => Command::Label,
+                    OpCode::Goto(opcode) => Command::Goto(label(opcode.id)?),
+                    OpCode::IfGoto(opcode) => Command::IfGoto(label(opcode.id)?),
+                    OpCode::Function(opcode) => Command::Function(opcode.vars_count),
+                    OpCode::Call(opcode) => {
+                        let callee = match (self.functions.get(opcode.id), os::builtin(opcode.id)) {
+                            (Some(ip), _) => Callee::Function(*ip),
+                            (None, Some(builtin)) if builtin.arity == opcode.args_count => {
+                                Callee::Builtin(builtin)
+                            }
+                            (None, Some(builtin)) => {
+                                return Err(format!(
+                                    "`{}` calls `{}` with {} arguments instead of {}",
+                                    function, opcode.id, opcode.args_count, builtin.arity
+                                ))
+                            }
+                            (None, None) => {
+                                return Err(format!(
+                                    "`{}` calls `{}` which is not defined",
+                                    function, opcode.id
+                                ))
+                            }
+                        };
+
+                        Command::Call(callee, opcode.args_count)
+                    }
+                };
+
+                self.program.push(Instruction {
+                    command,
+                    text: opcode.to_string(),
+                    function: function.clone(),
+                    file: String::from(*file),
+                });
+            }
+
+            static_base += statics;
+        }
+
+        if self.program.len() >= RETURN_TO_EMULATOR {
+            return Err(format!(
+                "program of {} commands is too large",
+                self.program.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn function(&self, name: &str) -> Option<usize> {
+        self.functions.get(name).copied()
+    }
+
+    // Whether `Sys.halt` was called, the program ran past its last command or spins in
+    // a `label L; goto L` loop forever
+    pub fn is_halted(&self) -> bool {
+        if self.halted || self.pc >= self.program.len() {
+            return true;
+        }
+
+        match self.program[self.pc].command {
+            Command::Goto(target) if target <= self.pc => self.program[target..self.pc]
+                .iter()
+                .all(|instruction| matches!(instruction.command, Command::Label)),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub fn ram(&self, address: u16) -> u16 {
+        self.ram.get(address as usize).copied().unwrap_or(0)
+    }
+
+    pub fn set_ram(&mut self, address: u16, value: u16) {
+        if let Some(cell) = self.ram.get_mut(address as usize) {
+            *cell = value;
+        }
+    }
+
+    pub fn screen(&self) -> &[u16] {
+        &self.ram[SCREEN as usize..KBD as usize]
+    }
+
+    pub(crate) fn screen_mut(&mut self) -> &mut [u16] {
+        &mut self.ram[SCREEN as usize..KBD as usize]
+    }
+
+    pub fn keyboard(&self) -> u16 {
+        self.ram[KBD as usize]
+    }
+
+    pub fn set_keyboard(&mut self, key: u16) {
+        self.ram[KBD as usize] = key;
+    }
+
+    pub fn set_real_time(&mut self, real_time: bool) {
+        self.os.real_time = real_time;
+    }
+
+    pub(crate) fn read(&self, address: u16) -> Result<u16, String> {
+        match self.ram.get(address as usize) {
+            Some(value) => Ok(*value),
+            None => Err(format!("RAM[{}] is out of memory", address)),
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u16) -> Result<(), String> {
+        match self.ram.get_mut(address as usize) {
+            Some(cell) => {
+                *cell = value;
+                Ok(())
+            }
+            None => Err(format!("RAM[{}] is out of memory", address)),
+        }
+    }
+
+    fn push(&mut self, value: u16) -> Result<(), String> {
+        let sp = self.ram[SP as usize];
+        self.write(sp, value)?;
+        self.ram[SP as usize] = sp.wrapping_add(1);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16, String> {
+        let sp = self.ram[SP as usize].wrapping_sub(1);
+        self.ram[SP as usize] = sp;
+        self.read(sp)
+    }
+
+    fn address(&self, segment: Segment, i: u16) -> u16 {
+        match segment {
+            Segment::Local => self.ram[LCL as usize].wrapping_add(i),
+            Segment::Argument => self.ram[ARG as usize].wrapping_add(i),
+            Segment::This => self.ram[THIS as usize].wrapping_add(i),
+            Segment::That => self.ram[THAT as usize].wrapping_add(i),
+            Segment::Pointer => THIS + i,
+            Segment::Temp => TEMP + i,
+            Segment::Static(base) => base + i,
+            Segment::Constant => unreachable!("constants have no address"),
+        }
+    }
+
+    // Saves the return address and the segments of the caller
+    fn push_frame(&mut self, return_address: u16) -> Result<(), String> {
+        self.push(return_address)?;
+        for register in &[LCL, ARG, THIS, THAT] {
+            self.push(self.ram[*register as usize])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn step(&mut self) -> Result<(), String> {
+        // Labels are not commands of their own, they take no steps
+        while matches!(
+            self.program
+                .get(self.pc)
+                .map(|instruction| instruction.command),
+            Some(Command::Label)
+        ) {
+            self.pc += 1;
+        }
+
+        if self.is_halted() {
+            return Ok(());
+        }
+
+        let pc = self.pc;
+        let mut next = pc + 1;
+
+        match self.program[pc].command {
+            Command::Arithmetic(operation) => {
+                let y = self.pop()?;
+                let value = match operation {
+                    Operation::Neg => y.wrapping_neg(),
+                    Operation::Not => !y,
+                    _ => {
+                        let x = self.pop()?;
+                        match operation {
+                            Operation::Add => x.wrapping_add(y),
+                            Operation::Sub => x.wrapping_sub(y),
+                            Operation::And => x & y,
+                            Operation::Or => x | y,
+                            Operation::Eq => truth(x == y),
+                            Operation::Gt => truth((x as i16) > (y as i16)),
+                            Operation::Lt => truth((x as i16) < (y as i16)),
+                            Operation::Neg | Operation::Not => unreachable!(),
+                        }
+                    }
+                };
+
+                self.push(value)?;
+            }
+            Command::Push(Segment::Constant, i) => self.push(i)?,
+            Command::Push(segment, i) => {
+                let value = self.read(self.address(segment, i))?;
+                self.push(value)?;
+            }
+            Command::Pop(segment, i) => {
+                let address = self.address(segment, i);
+                let value = self.pop()?;
+                self.write(address, value)?;
+            }
+            Command::Label => (),
+            Command::Goto(target) => next = target,
+            Command::IfGoto(target) => {
+                if self.pop()? != 0 {
+                    next = target;
+                }
+            }
+            Command::Function(locals) => {
+                for _ in 0..locals {
+                    self.push(0)?;
+                }
+            }
+            Command::Call(Callee::Function(target), arguments) => {
+                self.push_frame(next as u16)?;
+                let sp = self.ram[SP as usize];
+                self.ram[ARG as usize] = sp.wrapping_sub(arguments + 5);
+                self.ram[LCL as usize] = sp;
+                next = target;
+            }
+            Command::Call(Callee::Builtin(builtin), arguments) => {
+                let sp = self.ram[SP as usize].wrapping_sub(arguments);
+                let arguments = (0..arguments)
+                    .map(|i| self.read(sp.wrapping_add(i)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match builtin.call(self, &arguments)? {
+                    Some(value) => {
+                        self.ram[SP as usize] = sp;
+                        self.push(value)?;
+                    }
+                    // Waits for the keyboard or for time to pass, the call is made again on the next step
+                    None => next = pc,
+                }
+            }
+            Command::Return => {
+                let frame = self.ram[LCL as usize];
+                let return_address = self.read(frame.wrapping_sub(5))?;
+                let value = self.pop()?;
+                let arg = self.ram[ARG as usize];
+
+                self.write(arg, value)?;
+                self.ram[SP as usize] = arg.wrapping_add(1);
+                for (offset, register) in [THAT, THIS, ARG, LCL].iter().enumerate() {
+                    self.ram[*register as usize] =
+                        self.read(frame.wrapping_sub(offset as u16 + 1))?;
+                }
+
+                next = return_address as usize;
+            }
+        }
+
+        self.pc = next;
+        self.steps += 1;
+        Ok(())
+    }
+
+    pub fn run(&mut self, steps: u64) -> Result<(), String> {
+        for _ in 0..steps {
+            if self.is_halted() {
+                break;
+            }
+
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    // Calls a function from the builtin OS, which may itself be builtin or a VM function,
+    // running the VM function to its return
+    pub(crate) fn invoke(&mut self, name: &str, arguments: &[u16]) -> Result<u16, String> {
+        let target = match self.functions.get(name) {
+            Some(target) => *target,
+            None => {
+                let builtin =
+                    os::builtin(name).ok_or_else(|| format!("`{}` is not defined", name))?;
+                return builtin
+                    .call(self, arguments)?
+                    .ok_or_else(|| format!("`{}` can not wait here", name));
+            }
+        };
+
+        let pc = self.pc;
+        for argument in arguments {
+            self.push(*argument)?;
+        }
+
+        self.push_frame(RETURN_TO_EMULATOR as u16)?;
+        let sp = self.ram[SP as usize];
+        self.ram[ARG as usize] = sp.wrapping_sub(arguments.len() as u16 + 5);
+        self.ram[LCL as usize] = sp;
+        self.pc = target;
+
+        let mut steps = 0;
+        while !self.is_halted() {
+            if steps == MAX_NESTED_STEPS {
+                return Err(format!("`{}` did not return after {} steps", name, steps));
+            }
+
+            self.step()?;
+            steps += 1;
+        }
+
+        // The function halted instead of returning, e.g. `Sys.error` of the Jack OS
+        if self.pc != RETURN_TO_EMULATOR {
+            self.halted = true;
+            return Ok(0);
+        }
+
+        self.pc = pc;
+        self.pop()
+    }
+}
+
+// Variables of the VM emulator scripts: `sp`, `local`, `argument`, `this`, `that`,
+// `local[i]`, `argument[i]`, `this[i]`, `that[i]`, `temp[i]` and `RAM[i]`
+impl Target for Vm {
+    fn get(&self, name: &str) -> Result<i32, String> {
+        let address = variable(self, name)?;
+        Ok(i32::from(self.ram(address) as i16))
+    }
+
+    fn set(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let address = variable(self, name)?;
+        self.set_ram(address, value as u16);
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &[String], _: &Path) -> Result<(), String> {
+        match command
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["vmstep"] => self.step(),
+            _ => Err(format!("unknown command `{}`", command.join(" "))),
+        }
+    }
+}
+
+fn variable(vm: &Vm, name: &str) -> Result<u16, String> {
+    let address = match name {
+        "sp" => Some(SP),
+        "local" => Some(LCL),
+        "argument" => Some(ARG),
+        "this" => Some(THIS),
+        "that" => Some(THAT),
+        _ => match indexed(name) {
+            Some(("RAM", address)) => Some(address),
+            Some(("temp", i)) if i < 8 => Some(TEMP + i),
+            Some(("local", i)) => Some(vm.address(Segment::Local, i)),
+            Some(("argument", i)) => Some(vm.address(Segment::Argument, i)),
+            Some(("this", i)) => Some(vm.address(Segment::This, i)),
+            Some(("that", i)) => Some(vm.address(Segment::That, i)),
+            _ => None,
+        },
+    };
+
+    match address {
+        Some(address) if (address as usize) < RAM_SIZE => Ok(address),
+        _ => Err(format!("unknown variable `{}`", name)),
+    }
+}
+
+fn parse<'a>(name: &str, source: &'a str) -> Result<Vec<OpCode<'a>>, String> {
+    vm::parser::parse(name, source).map_err(|errors| {
+        errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+fn segment(name: opcode::Segment, i: u16, static_base: u16) -> Result<Segment, String> {
+    match (name, i) {
+        (opcode::Segment::Constant, _) => Ok(Segment::Constant),
+        (opcode::Segment::Local, _) => Ok(Segment::Local),
+        (opcode::Segment::Argument, _) => Ok(Segment::Argument),
+        (opcode::Segment::This, _) => Ok(Segment::This),
+        (opcode::Segment::That, _) => Ok(Segment::That),
+        (opcode::Segment::Pointer, _) => Ok(Segment::Pointer),
+        (opcode::Segment::Temp, 0..=7) => Ok(Segment::Temp),
+        (opcode::Segment::Static, _) => Ok(Segment::Static(static_base)),
+        (opcode::Segment::Temp, _) => Err(format!("`{} {}` is out of the segment", name, i)),
+    }
+}
+
+fn truth(value: bool) -> u16 {
+    if value {
+        0xFFFF
+    } else {
+        0
+    }
+}
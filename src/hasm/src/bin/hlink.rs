@@ -0,0 +1,6 @@
+use hasm::config::LinkerConfig;
+
+fn main() {
+    let config = LinkerConfig::from_args();
+    hasm::link(config);
+}
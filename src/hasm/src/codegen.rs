@@ -1,73 +1,67 @@
+use crate::error::*;
 use crate::instructions::*;
 use std::collections::HashMap;
 
 #[allow(clippy::implicit_hasher)]
-pub fn codegen(instructions: &[Instruction], symbol_table: &HashMap<&str, u16>) -> String {
-    let mut machine_code = String::new();
+pub fn encode(instruction: &Instruction, symbol_table: &HashMap<&str, u16>) -> Option<u16> {
+    match instruction {
+        Instruction::A(AInstruction::Literal(address)) => Some(*address),
+        Instruction::A(AInstruction::Mnemonic(name))
+        | Instruction::A(AInstruction::Expression(name)) => symbol_table.get(name).copied(),
+        Instruction::C(instruction) => Some(instruction.bits()),
+        Instruction::Label(_) | Instruction::Constant(_) | Instruction::Global(_) => None,
+    }
+}
+
+#[allow(clippy::implicit_hasher)]
+pub fn codegen(
+    statements: &[Statement],
+    symbol_table: &HashMap<&str, u16>,
+) -> Result<Vec<u16>, Vec<Error>> {
+    let mut machine_code: Vec<u16> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
 
-    for instruction in instructions {
-        match instruction {
+    for statement in statements {
+        match &statement.instruction {
             Instruction::A(instruction) => {
                 let address: u16 = match instruction {
                     AInstruction::Literal(address) => *address,
-                    AInstruction::Mnemonic(name) => *symbol_table
-                        .get(name)
-                        .unwrap_or_else(|| panic!("Unresolved symbol: {}", name)),
+                    AInstruction::Mnemonic(name) | AInstruction::Expression(name) => {
+                        match symbol_table.get(name) {
+                            Some(address) => *address,
+                            None => {
+                                errors.push(Error::new(
+                                    ErrorKind::UnresolvedSymbol(String::from(*name)),
+                                    &statement.span,
+                                ));
+                                continue;
+                            }
+                        }
+                    }
                 };
 
-                machine_code.push_str(&format!("{:016b}\n", address));
+                machine_code.push(address);
             }
 
             Instruction::C(instruction) => {
-                let dest = &instruction.dest;
-                let dest: &str = &format!(
-                    "{}{}{}",
-                    dest.a_register as u8, dest.d_register as u8, dest.ram as u8
-                );
-
-                let jump = &instruction.jump;
-                let jump: &str = &format!(
-                    "{}{}{}",
-                    jump.lower_than as u8, jump.equal as u8, jump.greater_than as u8
-                );
-
-                let comp = match instruction.comp {
-                    CCompInstruction::Zero => "0101010",
-                    CCompInstruction::One => "0111111",
-                    CCompInstruction::MinusOne => "0111010",
-                    CCompInstruction::DRegister => "0001100",
-                    CCompInstruction::ARegister => "0110000",
-                    CCompInstruction::NotDRegister => "0001101",
-                    CCompInstruction::NotARegister => "0110001",
-                    CCompInstruction::MinusDRegister => "0001111",
-                    CCompInstruction::MinusARegister => "0110011",
-                    CCompInstruction::DRegisterPlusOne => "0011111",
-                    CCompInstruction::ARegisterPlusOne => "0110111",
-                    CCompInstruction::DRegisterMinusOne => "0001110",
-                    CCompInstruction::ARegisterMinusOne => "0110010",
-                    CCompInstruction::DRegisterPlusARegister => "0000010",
-                    CCompInstruction::DRegisterMinusARegister => "0010011",
-                    CCompInstruction::ARegisterMinusDRegister => "0000111",
-                    CCompInstruction::DRegisterAndARegister => "0000000",
-                    CCompInstruction::DRegisterOrARegister => "0010101",
-                    CCompInstruction::RAM => "1110000",
-                    CCompInstruction::NotRAM => "1110001",
-                    CCompInstruction::MinusRAM => "1110011",
-                    CCompInstruction::RAMPlusOne => "1110111",
-                    CCompInstruction::RAMMinusOne => "1110010",
-                    CCompInstruction::DRegisterPlusRAM => "1000010",
-                    CCompInstruction::DRegisterMinusRAM => "1010011",
-                    CCompInstruction::RAMMinusDRegister => "1000111",
-                    CCompInstruction::DRegisterAndRAM => "1000000",
-                    CCompInstruction::DRegisterOrRAM => "1010101",
-                };
-
-                machine_code.push_str(&format!("111{}{}{}\n", comp, dest, jump));
+                machine_code.push(instruction.bits());
             }
 
-            Instruction::Label(_) => (),
+            Instruction::Label(_) | Instruction::Constant(_) | Instruction::Global(_) => (),
         }
     }
 
+    if errors.is_empty() {
+        Ok(machine_code)
+    } else {
+        Err(errors)
+    }
+}
+
+// Textual `.hack` format, one 16-character binary word per line
+pub fn print(machine_code: &[u16]) -> String {
     machine_code
+        .iter()
+        .map(|word| format!("{:016b}\n", word))
+        .collect()
 }
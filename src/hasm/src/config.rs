@@ -1,13 +1,34 @@
+use crate::format::Format;
+use crate::instructions::Isa;
+
 pub struct Config {
-    pub input_file: String,
+    pub input_files: Vec<String>,
     pub output_file: String,
+    pub listing_file: Option<String>,
+    pub debug_info_file: Option<String>,
+    pub isa: Isa,
+    pub disassemble: bool,
+    pub expand: bool,
+    pub object: bool,
+    pub warnings: bool,
+    pub format: Format,
+    pub optimize: bool,
 }
 
 impl Config {
-    pub fn new(input_file: &str, output_file: &str) -> Config {
+    pub fn new(input_files: &[&str], output_file: &str) -> Config {
         Config {
-            input_file: String::from(input_file),
+            input_files: input_files.iter().map(|file| String::from(*file)).collect(),
             output_file: String::from(output_file),
+            listing_file: None,
+            debug_info_file: None,
+            isa: Isa::Standard,
+            disassemble: false,
+            expand: false,
+            object: false,
+            warnings: true,
+            format: Format::Hack,
+            optimize: false,
         }
     }
 
@@ -16,18 +37,107 @@ impl Config {
             .version(clap::crate_version!())
             .author("Eugene Obrezkov <ghaiklor@gmail.com>")
             .about("An assembler for the Hack Assembly Language")
-            .arg_from_usage("-i --input=<INPUT-FILE> 'Set an input file where assembly code persists'")
-            .arg_from_usage("-o --output=<OUTPUT-FILE> 'Set an output file where translated code will be stored'")
+            .arg_from_usage("-i --input=<INPUT-FILE>... 'Set input files where assembly code persists, concatenated in order, - for stdin'")
+            .arg_from_usage("-o --output=<OUTPUT-FILE> 'Set an output file where translated code will be stored, - for stdout'")
+            .arg_from_usage("-l --listing=[LISTING-FILE] 'Write addresses, encodings and source lines side by side into a file'")
+            .arg_from_usage("-g --debug-info=[DEBUG-INFO-FILE] 'Write the source file, line and VM/Jack origin of every ROM address'")
+            .arg(
+                clap::Arg::from_usage("--isa=[ISA] 'Set the instruction set, extended one adds shifts'")
+                    .possible_values(&["standard", "extended"])
+                    .default_value("standard"),
+            )
+            .arg_from_usage("-d --disassemble 'Translate binary code from the input file back into assembly'")
+            .arg_from_usage("-E --expand 'Only expand macros and includes, emitting plain assembly'")
+            .arg_from_usage("--no-warnings 'Do not warn about suspicious assembly patterns'")
+            .arg_from_usage("-O --optimize 'Remove redundant instructions before generating code'")
+            .arg_from_usage("-c --object 'Emit a relocatable object to be linked by hlink'")
+            .arg(
+                clap::Arg::from_usage("-f --format=[FORMAT] 'Set the format of the machine code output'")
+                    .possible_values(&Format::NAMES)
+                    .default_value("hack"),
+            )
+            .get_matches();
+
+        let input_files: Vec<&str> = matches
+            .values_of("input")
+            .expect("Missing --input parameter")
+            .collect();
+
+        let output_file = matches
+            .value_of("output")
+            .expect("Missing --output parameter");
+
+        let mut config = Config::new(&input_files, output_file);
+        config.listing_file = matches.value_of("listing").map(String::from);
+        config.debug_info_file = matches.value_of("debug-info").map(String::from);
+        config.isa = match matches.value_of("isa") {
+            Some("extended") => Isa::Extended,
+            _ => Isa::Standard,
+        };
+        config.disassemble = matches.is_present("disassemble");
+        config.expand = matches.is_present("expand");
+        config.object = matches.is_present("object");
+        config.optimize = matches.is_present("optimize");
+        config.warnings = !matches.is_present("no-warnings");
+        config.format = matches
+            .value_of("format")
+            .and_then(Format::from_name)
+            .unwrap_or(Format::Hack);
+
+        config
+    }
+}
+
+pub struct LinkerConfig {
+    pub input_files: Vec<String>,
+    pub output_file: String,
+    pub format: Format,
+}
+
+impl LinkerConfig {
+    pub fn new(input_files: &[&str], output_file: &str) -> LinkerConfig {
+        LinkerConfig {
+            input_files: input_files.iter().map(|file| String::from(*file)).collect(),
+            output_file: String::from(output_file),
+            format: Format::Hack,
+        }
+    }
+
+    pub fn from_args() -> LinkerConfig {
+        let matches = clap::App::new("hlink")
+            .version(clap::crate_version!())
+            .author("Eugene Obrezkov <ghaiklor@gmail.com>")
+            .about("A linker for relocatable objects produced by hasm")
+            .arg_from_usage(
+                "-o --output=<OUTPUT-FILE> 'Set an output file where linked code will be stored'",
+            )
+            .arg(
+                clap::Arg::from_usage(
+                    "-f --format=[FORMAT] 'Set the format of the machine code output'",
+                )
+                .possible_values(&Format::NAMES)
+                .default_value("hack"),
+            )
+            .arg_from_usage(
+                "<INPUT-FILES>... 'Set object files to link, in the order they are placed in ROM'",
+            )
             .get_matches();
 
-        let input_file = matches
-            .value_of("input")
-            .expect("Missing --input parameter");
+        let input_files: Vec<&str> = matches
+            .values_of("INPUT-FILES")
+            .expect("Missing input files")
+            .collect();
 
         let output_file = matches
             .value_of("output")
             .expect("Missing --output parameter");
 
-        Config::new(input_file, output_file)
+        let mut config = LinkerConfig::new(&input_files, output_file);
+        config.format = matches
+            .value_of("format")
+            .and_then(Format::from_name)
+            .unwrap_or(Format::Hack);
+
+        config
     }
 }
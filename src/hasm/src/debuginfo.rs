@@ -0,0 +1,208 @@
+use crate::error::*;
+use crate::expr;
+use crate::instructions::*;
+use crate::macros::Expansion;
+use crate::symbols::is_predefined;
+use std::collections::{HashMap, HashSet};
+
+// Where the instruction at some ROM address came from. Besides the assembly file and line,
+// `// vm: <command>` and `// jack: <location>` comments above the instruction are carried
+// over, so a debugger can show the VM command or Jack line the PC belongs to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub vm: Option<String>,
+    pub jack: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DebugInfo {
+    pub rom: Vec<Location>,
+    pub labels: Vec<(String, u16)>,
+    pub variables: Vec<(String, u16)>,
+}
+
+impl DebugInfo {
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, address)| *address)
+    }
+
+    pub fn variable(&self, name: &str) -> Option<u16> {
+        self.variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, address)| *address)
+    }
+}
+
+#[allow(clippy::implicit_hasher)]
+pub fn collect(
+    statements: &[Statement],
+    symbol_table: &HashMap<&str, u16>,
+    expansion: &Expansion,
+) -> DebugInfo {
+    let mut debug_info = DebugInfo::default();
+    let lines: Vec<&str> = expansion.code.lines().collect();
+    let mut next_line = 0;
+    let mut vm: Option<String> = None;
+    let mut jack: Option<String> = None;
+    let mut definitions: HashSet<&str> = HashSet::new();
+
+    for statement in statements {
+        // Origin comments apply to every instruction below them, up to the next one
+        while next_line < statement.span.line && next_line < lines.len() {
+            match origin_comment(lines[next_line]) {
+                Some(("vm", origin)) => vm = Some(String::from(origin)),
+                Some(("jack", origin)) => jack = Some(String::from(origin)),
+                _ => (),
+            }
+
+            next_line += 1;
+        }
+
+        match &statement.instruction {
+            Instruction::Label(label) => {
+                definitions.insert(label.name);
+                if debug_info.label(label.name).is_none() {
+                    debug_info
+                        .labels
+                        .push((String::from(label.name), symbol_table[label.name]));
+                }
+            }
+            Instruction::Constant(constant) => {
+                definitions.insert(constant.name);
+            }
+            Instruction::Global(_) => (),
+            Instruction::A(_) | Instruction::C(_) => {
+                let location = match expansion.origin(statement.span.line) {
+                    Some(origin) => (origin.file.clone(), origin.line),
+                    None => (String::new(), statement.span.line),
+                };
+
+                debug_info.rom.push(Location {
+                    file: location.0,
+                    line: location.1,
+                    vm: vm.clone(),
+                    jack: jack.clone(),
+                });
+            }
+        }
+    }
+
+    for statement in statements {
+        let names = match &statement.instruction {
+            Instruction::A(AInstruction::Mnemonic(name)) => vec![*name],
+            Instruction::A(AInstruction::Expression(expression)) => expr::symbols(expression),
+            _ => continue,
+        };
+
+        for name in names {
+            let is_variable = !is_predefined(name) && !definitions.contains(name);
+            if is_variable && debug_info.variable(name).is_none() {
+                debug_info
+                    .variables
+                    .push((String::from(name), symbol_table[name]));
+            }
+        }
+    }
+
+    debug_info
+}
+
+fn origin_comment(line: &str) -> Option<(&str, &str)> {
+    let comment = line[line.find("//")? + 2..].trim();
+    let colon = comment.find(':')?;
+    let (kind, origin) = (&comment[..colon], comment[colon + 1..].trim());
+
+    match kind {
+        "vm" | "jack" if !origin.is_empty() => Some((kind, origin)),
+        _ => None,
+    }
+}
+
+// Tab-separated lines: `rom <address> <file> <line> <vm> <jack>`, with the origins
+// left empty when unknown, then `label <name> <address>` and `var <name> <address>`
+pub fn print(debug_info: &DebugInfo) -> String {
+    let mut output = String::from("// Hack debug info\n");
+
+    for (address, location) in debug_info.rom.iter().enumerate() {
+        output.push_str(&format!(
+            "rom\t{}\t{}\t{}\t{}\t{}\n",
+            address,
+            location.file,
+            location.line,
+            location.vm.as_deref().unwrap_or(""),
+            location.jack.as_deref().unwrap_or("")
+        ));
+    }
+
+    for (name, address) in &debug_info.labels {
+        output.push_str(&format!("label\t{}\t{}\n", name, address));
+    }
+
+    for (name, address) in &debug_info.variables {
+        output.push_str(&format!("var\t{}\t{}\n", name, address));
+    }
+
+    output
+}
+
+pub fn parse(source: &str) -> Result<DebugInfo, Vec<Error>> {
+    let mut debug_info = DebugInfo::default();
+    let mut errors: Vec<Error> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let origin = |field: &str| match field {
+            "" => None,
+            field => Some(String::from(field)),
+        };
+
+        let parsed = match fields.as_slice() {
+            ["rom", address, file, line, vm, jack] => {
+                match (address.parse::<usize>(), line.parse()) {
+                    (Ok(address), Ok(line)) if address == debug_info.rom.len() => {
+                        debug_info.rom.push(Location {
+                            file: String::from(*file),
+                            line,
+                            vm: origin(vm),
+                            jack: origin(jack),
+                        });
+                        Some(())
+                    }
+                    _ => None,
+                }
+            }
+            ["label", name, address] => address
+                .parse()
+                .ok()
+                .map(|address| debug_info.labels.push((String::from(*name), address))),
+            ["var", name, address] => address
+                .parse()
+                .ok()
+                .map(|address| debug_info.variables.push((String::from(*name), address))),
+            _ => None,
+        };
+
+        if parsed.is_none() {
+            errors.push(Error::new(
+                ErrorKind::InvalidDebugInfo(String::from(line.trim())),
+                &Span::new(index + 1, line, line.trim()),
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(debug_info)
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::*;
+use crate::instructions::*;
+use std::collections::{BTreeSet, HashMap};
+
+// Reads the textual `.hack` format, one 16-character binary word per line
+pub fn parse(machine_code: &str) -> Result<Vec<u16>, Vec<Error>> {
+    let mut words: Vec<u16> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
+
+    for (index, line) in machine_code.lines().enumerate() {
+        let word = line.trim();
+        if word.is_empty() {
+            continue;
+        }
+
+        let is_binary = word.len() == 16 && word.chars().all(|c| c == '0' || c == '1');
+        match u16::from_str_radix(word, 2) {
+            Ok(word) if is_binary => words.push(word),
+            _ => errors.push(Error::new(
+                ErrorKind::InvalidMachineCode(String::from(word)),
+                &Span::new(index + 1, line, word),
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(words)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn disassemble(machine_code: &str) -> Result<String, Vec<Error>> {
+    let words = parse(machine_code)?;
+    let mut instructions: Vec<Instruction> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
+
+    let lines = machine_code
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    for ((index, line), word) in lines.zip(words) {
+        match Instruction::decode(word) {
+            Some(instruction) => instructions.push(instruction),
+            None => errors.push(Error::new(
+                ErrorKind::UnknownEncoding(word),
+                &Span::new(index + 1, line, line.trim()),
+            )),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let labels = jump_targets(&instructions);
+    let names: HashMap<u16, String> = labels
+        .iter()
+        .map(|address| (*address, format!("L{}", address)))
+        .collect();
+
+    let mut assembly = String::new();
+    for (ip, instruction) in instructions.iter().enumerate() {
+        if let Some(name) = names.get(&(ip as u16)) {
+            assembly.push_str(&format!("({})\n", name));
+        }
+
+        let instruction = match instruction {
+            Instruction::A(AInstruction::Literal(address)) => {
+                let next = instructions.get(ip + 1);
+                match (names.get(address), next) {
+                    (Some(name), Some(Instruction::C(next))) if !next.jump.is_empty() => {
+                        Instruction::A(AInstruction::Mnemonic(name))
+                    }
+                    (_, Some(Instruction::C(next))) if accesses_ram(next) => {
+                        match well_known_name(*address) {
+                            Some(name) => Instruction::A(AInstruction::Mnemonic(name)),
+                            None => *instruction,
+                        }
+                    }
+                    _ => *instruction,
+                }
+            }
+            _ => *instruction,
+        };
+
+        assembly.push_str(&format!("    {}\n", instruction));
+    }
+
+    // Jumps may target the address right after the last instruction
+    if let Some(name) = names.get(&(instructions.len() as u16)) {
+        assembly.push_str(&format!("({})\n", name));
+    }
+
+    Ok(assembly)
+}
+
+// Addresses loaded into A right before a jumping C-instruction
+fn jump_targets(instructions: &[Instruction]) -> BTreeSet<u16> {
+    instructions
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Instruction::A(AInstruction::Literal(address)), Instruction::C(next)]
+                if !next.jump.is_empty() && *address as usize <= instructions.len() =>
+            {
+                Some(*address)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn accesses_ram(instruction: &CInstruction) -> bool {
+    instruction.comp.uses_ram() || instruction.dest.ram
+}
+
+fn well_known_name(address: u16) -> Option<&'static str> {
+    const REGISTERS: [&str; 16] = [
+        "R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "R8", "R9", "R10", "R11", "R12", "R13",
+        "R14", "R15",
+    ];
+
+    match address {
+        0..=15 => Some(REGISTERS[address as usize]),
+        16384 => Some("SCREEN"),
+        24576 => Some("KBD"),
+        _ => None,
+    }
+}
@@ -0,0 +1,283 @@
+use crate::instructions::Span;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnknownComputation(String),
+    UnknownDestination(String),
+    UnknownJump(String),
+    ExtendedInstruction(String),
+    InvalidAddress(String),
+    InvalidSymbol(String),
+    InvalidLabel(String),
+    UnresolvedSymbol(String),
+    RomOverflow,
+    RamOverflow(String),
+    InvalidDirective(String),
+    UnexpectedDirective(String),
+    UnterminatedMacro(String),
+    MacroRedefinition(String),
+    MacroArity(String, usize, usize),
+    MacroRecursion(String),
+    IncludeNotFound(String),
+    IncludeCycle(String),
+    InvalidMachineCode(String),
+    InvalidObject(String),
+    CorruptedObject,
+    DuplicateSymbol(String),
+    UndefinedGlobal(String),
+    UnknownEncoding(u16),
+    InvalidExpression(String),
+    ExpressionOverflow(String),
+    AddressOverflow(String, i64),
+    SymbolRedefinition(String),
+    UnrelocatableExpression(String),
+    InvalidDebugInfo(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownComputation(mnemonic) => {
+                write!(f, "unknown computation `{}`", mnemonic)
+            }
+            ErrorKind::UnknownDestination(mnemonic) => {
+                write!(f, "unknown destination `{}`", mnemonic)
+            }
+            ErrorKind::UnknownJump(mnemonic) => write!(f, "unknown jump `{}`", mnemonic),
+            ErrorKind::ExtendedInstruction(mnemonic) => write!(
+                f,
+                "`{}` is only available in the extended instruction set (--isa extended)",
+                mnemonic
+            ),
+            ErrorKind::InvalidAddress(address) => {
+                write!(f, "`{}` is not a valid 16-bit address", address)
+            }
+            ErrorKind::InvalidSymbol(symbol) => write!(f, "invalid symbol name `{}`", symbol),
+            ErrorKind::InvalidLabel(label) => write!(f, "invalid label declaration `{}`", label),
+            ErrorKind::UnresolvedSymbol(symbol) => write!(f, "unresolved symbol `{}`", symbol),
+            ErrorKind::RomOverflow => write!(f, "program does not fit into 32K of ROM"),
+            ErrorKind::RamOverflow(symbol) => write!(
+                f,
+                "no RAM left to allocate variable `{}` below SCREEN",
+                symbol
+            ),
+            ErrorKind::InvalidDirective(line) => write!(f, "malformed directive `{}`", line),
+            ErrorKind::UnexpectedDirective(directive) => {
+                write!(f, "unexpected `{}` directive", directive)
+            }
+            ErrorKind::UnterminatedMacro(name) => {
+                write!(f, "macro `{}` is missing its `.endm`", name)
+            }
+            ErrorKind::MacroRedefinition(name) => {
+                write!(f, "macro `{}` is defined multiple times", name)
+            }
+            ErrorKind::MacroArity(name, expected, found) => write!(
+                f,
+                "macro `{}` takes {} argument(s) but {} were supplied",
+                name, expected, found
+            ),
+            ErrorKind::MacroRecursion(name) => {
+                write!(f, "recursion limit reached while expanding `{}`", name)
+            }
+            ErrorKind::IncludeNotFound(path) => {
+                write!(f, "could not read included file `{}`", path)
+            }
+            ErrorKind::IncludeCycle(path) => write!(f, "file `{}` includes itself", path),
+            ErrorKind::InvalidMachineCode(word) => {
+                write!(f, "`{}` is not a 16-bit binary word", word)
+            }
+            ErrorKind::InvalidObject(line) => write!(f, "malformed object file line `{}`", line),
+            ErrorKind::CorruptedObject => write!(
+                f,
+                "object file is corrupted, its relocations do not match the code"
+            ),
+            ErrorKind::DuplicateSymbol(symbol) => {
+                write!(f, "symbol `{}` is exported by several objects", symbol)
+            }
+            ErrorKind::UndefinedGlobal(symbol) => {
+                write!(f, "`.global {}` does not name a label of this file", symbol)
+            }
+            ErrorKind::UnknownEncoding(word) => {
+                write!(f, "`{:016b}` does not encode any instruction", word)
+            }
+            ErrorKind::InvalidExpression(expression) => {
+                write!(f, "invalid expression `{}`", expression)
+            }
+            ErrorKind::ExpressionOverflow(expression) => {
+                write!(f, "value of `{}` does not fit into 16 bits", expression)
+            }
+            ErrorKind::AddressOverflow(expression, value) if *value < 0 => write!(
+                f,
+                "value of `{}` is {}, A-instructions only take 0 to 32767, load {} and negate it with `A=-A` instead",
+                expression,
+                value,
+                -value
+            ),
+            ErrorKind::AddressOverflow(expression, value) => write!(
+                f,
+                "value of `{}` is {}, A-instructions only take 0 to 32767",
+                expression, value
+            ),
+            ErrorKind::SymbolRedefinition(symbol) => {
+                write!(f, "symbol `{}` is already defined", symbol)
+            }
+            ErrorKind::UnrelocatableExpression(expression) => write!(
+                f,
+                "`{}` can only refer to constants and labels of the same object",
+                expression
+            ),
+            ErrorKind::InvalidDebugInfo(line) => write!(f, "malformed debug info line `{}`", line),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub snippet: String,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, span: &Span) -> Error {
+        Error {
+            kind,
+            file: None,
+            line: span.line,
+            column: span.column,
+            length: span.length,
+            snippet: String::from(span.source),
+        }
+    }
+
+    // Errors that belong to a whole file rather than to a line in it
+    pub fn in_file(kind: ErrorKind, file: &str) -> Error {
+        Error {
+            kind,
+            file: Some(String::from(file)),
+            line: 0,
+            column: 0,
+            length: 0,
+            snippet: String::new(),
+        }
+    }
+
+    // The `file` is used only for errors that do not know where they come from
+    pub fn report(&self, file: &str) -> String {
+        let file = self.file.as_deref().unwrap_or(file);
+        render(
+            "error",
+            &self.kind,
+            file,
+            self.line,
+            self.column,
+            self.length,
+            &self.snippet,
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}: {}", file, self.line, self.column, self.kind),
+            None => write!(f, "{}:{}: {}", self.line, self.column, self.kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarningKind {
+    UnusedLabel(String),
+    SingleUseSymbol(String),
+    DuplicateLabel(String),
+    JumpWithADestination,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WarningKind::UnusedLabel(label) => write!(f, "label `{}` is never used", label),
+            WarningKind::SingleUseSymbol(symbol) => write!(
+                f,
+                "symbol `{}` is used only once and becomes a new variable, is it a typo?",
+                symbol
+            ),
+            WarningKind::DuplicateLabel(label) => write!(
+                f,
+                "label `{}` is defined again and overrides the previous definition",
+                label
+            ),
+            WarningKind::JumpWithADestination => write!(
+                f,
+                "jump together with an assignment to A jumps to the old value of A"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub snippet: String,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, span: &Span) -> Warning {
+        Warning {
+            kind,
+            file: None,
+            line: span.line,
+            column: span.column,
+            length: span.length,
+            snippet: String::from(span.source),
+        }
+    }
+
+    pub fn report(&self, file: &str) -> String {
+        let file = self.file.as_deref().unwrap_or(file);
+        render(
+            "warning",
+            &self.kind,
+            file,
+            self.line,
+            self.column,
+            self.length,
+            &self.snippet,
+        )
+    }
+}
+
+// Renders a diagnostic the way rustc does, pointing at the offending part of the line
+fn render(
+    level: &str,
+    message: &dyn fmt::Display,
+    file: &str,
+    line: usize,
+    column: usize,
+    length: usize,
+    snippet: &str,
+) -> String {
+    if line == 0 {
+        return format!("{}: {}\n --> {}\n", level, message, file);
+    }
+
+    let gutter = " ".repeat(line.to_string().len());
+    let padding = " ".repeat(column.saturating_sub(1));
+    let carets = "^".repeat(length.max(1));
+
+    format!(
+        "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+        level, message, gutter, file, line, column, gutter, line, snippet, gutter, padding, carets
+    )
+}
@@ -0,0 +1,203 @@
+use crate::error::ErrorKind;
+
+// Constant expressions allowed in A-instructions and `.equ` directives, e.g. `SCREEN+32*10`.
+// `/` starts a comment in Hack assembly, so there is no division
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression<'a> {
+    Number(i64),
+    Symbol(&'a str),
+    Negate(Box<Expression<'a>>),
+    Add(Box<Expression<'a>>, Box<Expression<'a>>),
+    Subtract(Box<Expression<'a>>, Box<Expression<'a>>),
+    Multiply(Box<Expression<'a>>, Box<Expression<'a>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Number(i64),
+    Symbol(&'a str),
+    Operator(char),
+}
+
+pub fn parse(text: &str) -> Option<Expression<'_>> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, index: 0 };
+    let expression = parser.sum()?;
+
+    if parser.index == parser.tokens.len() {
+        Some(expression)
+    } else {
+        None
+    }
+}
+
+// Symbols the expression refers to, in the order of appearance
+pub fn symbols(text: &str) -> Vec<&str> {
+    tokenize(text)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Symbol(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+// Evaluates the expression into a 16-bit word, negative values are stored in two's complement.
+// Symbol values are read back as signed, so `.equ` constants can be negative too
+pub fn evaluate<F>(text: &str, lookup: F) -> Result<u16, ErrorKind>
+where
+    F: Fn(&str) -> Option<u16>,
+{
+    match value_of(text, &lookup)? {
+        value if (-32768..=65535).contains(&value) => Ok(value as u16),
+        _ => Err(ErrorKind::ExpressionOverflow(String::from(text))),
+    }
+}
+
+// Evaluates the expression of an A-instruction, which only has 15 bits for the value.
+// Anything else would set the top bit and turn the instruction into a C-instruction
+pub fn evaluate_address<F>(text: &str, lookup: F) -> Result<u16, ErrorKind>
+where
+    F: Fn(&str) -> Option<u16>,
+{
+    match value_of(text, &lookup)? {
+        value if (0..=32767).contains(&value) => Ok(value as u16),
+        value => Err(ErrorKind::AddressOverflow(String::from(text), value)),
+    }
+}
+
+fn value_of<F>(text: &str, lookup: &F) -> Result<i64, ErrorKind>
+where
+    F: Fn(&str) -> Option<u16>,
+{
+    let expression = parse(text).ok_or_else(|| ErrorKind::InvalidExpression(String::from(text)))?;
+
+    expression
+        .value(lookup)?
+        .ok_or_else(|| ErrorKind::ExpressionOverflow(String::from(text)))
+}
+
+impl<'a> Expression<'a> {
+    // `None` when an intermediate result does not fit into 64 bits
+    fn value<F>(&self, lookup: &F) -> Result<Option<i64>, ErrorKind>
+    where
+        F: Fn(&str) -> Option<u16>,
+    {
+        let value = match self {
+            Expression::Number(number) => Some(*number),
+            Expression::Symbol(name) => match lookup(name) {
+                Some(value) => Some(i64::from(value as i16)),
+                None => return Err(ErrorKind::UnresolvedSymbol(String::from(*name))),
+            },
+            Expression::Negate(operand) => operand.value(lookup)?.and_then(i64::checked_neg),
+            Expression::Add(lhs, rhs) => both(lhs.value(lookup)?, rhs.value(lookup)?)
+                .and_then(|(lhs, rhs)| lhs.checked_add(rhs)),
+            Expression::Subtract(lhs, rhs) => both(lhs.value(lookup)?, rhs.value(lookup)?)
+                .and_then(|(lhs, rhs)| lhs.checked_sub(rhs)),
+            Expression::Multiply(lhs, rhs) => both(lhs.value(lookup)?, rhs.value(lookup)?)
+                .and_then(|(lhs, rhs)| lhs.checked_mul(rhs)),
+        };
+
+        Ok(value)
+    }
+}
+
+fn both(lhs: Option<i64>, rhs: Option<i64>) -> Option<(i64, i64)> {
+    Some((lhs?, rhs?))
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_.$:".contains(c)
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if "+-*()".contains(c) {
+            tokens.push(Token::Operator(c));
+            1
+        } else if is_symbol_char(c) {
+            let length = rest.find(|c| !is_symbol_char(c)).unwrap_or(rest.len());
+            let word = &rest[..length];
+
+            if c.is_ascii_digit() {
+                tokens.push(Token::Number(word.parse().ok()?));
+            } else {
+                tokens.push(Token::Symbol(word));
+            }
+
+            length
+        } else {
+            return None;
+        };
+
+        rest = rest[length..].trim_start();
+    }
+
+    Some(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn sum(&mut self) -> Option<Expression<'a>> {
+        let mut expression = self.product()?;
+
+        loop {
+            expression = match self.peek() {
+                Some(Token::Operator('+')) => {
+                    self.index += 1;
+                    Expression::Add(Box::new(expression), Box::new(self.product()?))
+                }
+                Some(Token::Operator('-')) => {
+                    self.index += 1;
+                    Expression::Subtract(Box::new(expression), Box::new(self.product()?))
+                }
+                _ => return Some(expression),
+            };
+        }
+    }
+
+    fn product(&mut self) -> Option<Expression<'a>> {
+        let mut expression = self.unary()?;
+
+        while let Some(Token::Operator('*')) = self.peek() {
+            self.index += 1;
+            expression = Expression::Multiply(Box::new(expression), Box::new(self.unary()?));
+        }
+
+        Some(expression)
+    }
+
+    fn unary(&mut self) -> Option<Expression<'a>> {
+        let token = self.peek()?;
+        self.index += 1;
+
+        match token {
+            Token::Number(number) => Some(Expression::Number(number)),
+            Token::Symbol(name) => Some(Expression::Symbol(name)),
+            Token::Operator('-') => Some(Expression::Negate(Box::new(self.unary()?))),
+            Token::Operator('(') => {
+                let expression = self.sum()?;
+                match self.peek() {
+                    Some(Token::Operator(')')) => {
+                        self.index += 1;
+                        Some(expression)
+                    }
+                    _ => None,
+                }
+            }
+            Token::Operator(_) => None,
+        }
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.index).copied()
+    }
+}
@@ -0,0 +1,136 @@
+use crate::codegen;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Hack,
+    RawLittleEndian,
+    RawBigEndian,
+    IntelHex,
+    Logisim,
+    Verilog,
+    Rust,
+    C,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 8] = [
+        "hack", "raw-le", "raw-be", "ihex", "logisim", "verilog", "rust", "c",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "hack" => Some(Format::Hack),
+            "raw-le" => Some(Format::RawLittleEndian),
+            "raw-be" => Some(Format::RawBigEndian),
+            "ihex" => Some(Format::IntelHex),
+            "logisim" => Some(Format::Logisim),
+            "verilog" => Some(Format::Verilog),
+            "rust" => Some(Format::Rust),
+            "c" => Some(Format::C),
+            _ => None,
+        }
+    }
+}
+
+pub fn format(machine_code: &[u16], format: Format) -> Vec<u8> {
+    match format {
+        Format::Hack => codegen::print(machine_code).into_bytes(),
+        Format::RawLittleEndian => machine_code.iter().flat_map(|w| w.to_le_bytes()).collect(),
+        Format::RawBigEndian => machine_code.iter().flat_map(|w| w.to_be_bytes()).collect(),
+        Format::IntelHex => intel_hex(machine_code).into_bytes(),
+        Format::Logisim => logisim(machine_code).into_bytes(),
+        Format::Verilog => verilog(machine_code).into_bytes(),
+        Format::Rust => rust(machine_code).into_bytes(),
+        Format::C => c(machine_code).into_bytes(),
+    }
+}
+
+// One word per data record, addressed by words the way FPGA tools initialize 16-bit ROMs
+fn intel_hex(machine_code: &[u16]) -> String {
+    let mut output = String::new();
+
+    for (address, word) in machine_code.iter().enumerate() {
+        let bytes = [
+            0x02,
+            (address >> 8) as u8,
+            address as u8,
+            0x00,
+            (word >> 8) as u8,
+            *word as u8,
+        ];
+
+        let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        let record: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        output.push_str(&format!(":{}{:02X}\n", record, sum.wrapping_neg()));
+    }
+
+    output.push_str(":00000001FF\n");
+    output
+}
+
+// Logisim memory image, repeated words are run-length encoded as `count*word`
+fn logisim(machine_code: &[u16]) -> String {
+    let mut words: Vec<String> = Vec::new();
+    let mut index = 0;
+
+    while index < machine_code.len() {
+        let word = machine_code[index];
+        let run = machine_code[index..]
+            .iter()
+            .take_while(|next| **next == word)
+            .count();
+
+        if run >= 4 {
+            words.push(format!("{}*{:x}", run, word));
+            index += run;
+        } else {
+            words.push(format!("{:x}", word));
+            index += 1;
+        }
+    }
+
+    let mut output = String::from("v2.0 raw\n");
+    for line in words.chunks(8) {
+        output.push_str(&line.join(" "));
+        output.push('\n');
+    }
+
+    output
+}
+
+// Memory file for Verilog `$readmemb`
+fn verilog(machine_code: &[u16]) -> String {
+    let mut output = format!("// Hack ROM image, {} words\n@0\n", machine_code.len());
+
+    for (address, word) in machine_code.iter().enumerate() {
+        output.push_str(&format!("{:016b} // {}\n", word, address));
+    }
+
+    output
+}
+
+fn rust(machine_code: &[u16]) -> String {
+    format!(
+        "pub const ROM: [u16; {}] = [\n{}];\n",
+        machine_code.len(),
+        hex_words(machine_code)
+    )
+}
+
+fn c(machine_code: &[u16]) -> String {
+    format!(
+        "#include <stdint.h>\n\nconst uint16_t ROM[{}] = {{\n{}}};\n",
+        machine_code.len(),
+        hex_words(machine_code)
+    )
+}
+
+fn hex_words(machine_code: &[u16]) -> String {
+    machine_code
+        .chunks(8)
+        .map(|line| {
+            let words: Vec<String> = line.iter().map(|word| format!("0x{:04X},", word)).collect();
+            format!("    {}\n", words.join(" "))
+        })
+        .collect()
+}
@@ -1,24 +1,27 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AInstruction<'a> {
     Literal(u16),
     Mnemonic(&'a str),
+    Expression(&'a str),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CDestinationInstruction {
     pub ram: bool,
     pub a_register: bool,
     pub d_register: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CJumpInstruction {
     pub greater_than: bool,
     pub equal: bool,
     pub lower_than: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CCompInstruction {
     Zero,
     One,
@@ -48,24 +51,329 @@ pub enum CCompInstruction {
     DRegisterAndRAM,
     DRegisterOrARegister,
     DRegisterOrRAM,
+    DRegisterShiftLeft,
+    ARegisterShiftLeft,
+    RAMShiftLeft,
+    DRegisterShiftRight,
+    ARegisterShiftRight,
+    RAMShiftRight,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CInstruction {
     pub dest: CDestinationInstruction,
     pub comp: CCompInstruction,
     pub jump: CJumpInstruction,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LabelInstruction<'a> {
     pub name: &'a str,
     pub ptr: u16,
 }
 
-#[derive(Debug)]
+// `.equ NAME value` or `.define NAME value`, the value is an expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstantInstruction<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+// `.global NAME`, the label is exported from a relocatable object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalInstruction<'a> {
+    pub name: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Instruction<'a> {
     A(AInstruction<'a>),
     C(CInstruction),
     Label(LabelInstruction<'a>),
+    Constant(ConstantInstruction<'a>),
+    Global(GlobalInstruction<'a>),
+}
+
+// Computations with their mnemonics and `a c1 c2 c3 c4 c5 c6` bits
+static COMPUTATIONS: [(CCompInstruction, &str, u16); 28] = [
+    (CCompInstruction::Zero, "0", 0b010_1010),
+    (CCompInstruction::One, "1", 0b011_1111),
+    (CCompInstruction::MinusOne, "-1", 0b011_1010),
+    (CCompInstruction::DRegister, "D", 0b000_1100),
+    (CCompInstruction::ARegister, "A", 0b011_0000),
+    (CCompInstruction::RAM, "M", 0b111_0000),
+    (CCompInstruction::NotDRegister, "!D", 0b000_1101),
+    (CCompInstruction::NotARegister, "!A", 0b011_0001),
+    (CCompInstruction::NotRAM, "!M", 0b111_0001),
+    (CCompInstruction::MinusDRegister, "-D", 0b000_1111),
+    (CCompInstruction::MinusARegister, "-A", 0b011_0011),
+    (CCompInstruction::MinusRAM, "-M", 0b111_0011),
+    (CCompInstruction::DRegisterPlusOne, "D+1", 0b001_1111),
+    (CCompInstruction::ARegisterPlusOne, "A+1", 0b011_0111),
+    (CCompInstruction::RAMPlusOne, "M+1", 0b111_0111),
+    (CCompInstruction::DRegisterMinusOne, "D-1", 0b000_1110),
+    (CCompInstruction::ARegisterMinusOne, "A-1", 0b011_0010),
+    (CCompInstruction::RAMMinusOne, "M-1", 0b111_0010),
+    (CCompInstruction::DRegisterPlusARegister, "D+A", 0b000_0010),
+    (CCompInstruction::DRegisterPlusRAM, "D+M", 0b100_0010),
+    (CCompInstruction::DRegisterMinusARegister, "D-A", 0b001_0011),
+    (CCompInstruction::DRegisterMinusRAM, "D-M", 0b101_0011),
+    (CCompInstruction::ARegisterMinusDRegister, "A-D", 0b000_0111),
+    (CCompInstruction::RAMMinusDRegister, "M-D", 0b100_0111),
+    (CCompInstruction::DRegisterAndARegister, "D&A", 0b000_0000),
+    (CCompInstruction::DRegisterAndRAM, "D&M", 0b100_0000),
+    (CCompInstruction::DRegisterOrARegister, "D|A", 0b001_0101),
+    (CCompInstruction::DRegisterOrRAM, "D|M", 0b101_0101),
+];
+
+// Shifts of the extended instruction set, encoded with the `101` prefix
+static SHIFTS: [(CCompInstruction, &str, u16); 6] = [
+    (CCompInstruction::DRegisterShiftLeft, "D<<", 0b011_0000),
+    (CCompInstruction::ARegisterShiftLeft, "A<<", 0b010_0000),
+    (CCompInstruction::RAMShiftLeft, "M<<", 0b110_0000),
+    (CCompInstruction::DRegisterShiftRight, "D>>", 0b001_0000),
+    (CCompInstruction::ARegisterShiftRight, "A>>", 0b000_0000),
+    (CCompInstruction::RAMShiftRight, "M>>", 0b100_0000),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Isa {
+    Standard,
+    Extended,
+}
+
+const JUMPS: [&str; 8] = ["", "JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
+
+impl CDestinationInstruction {
+    pub fn from_mnemonic(mnemonic: &str) -> Option<CDestinationInstruction> {
+        let valid = !mnemonic.is_empty()
+            && mnemonic.chars().all(|c| "AMD".contains(c))
+            && mnemonic.chars().all(|c| mnemonic.matches(c).count() == 1);
+
+        if !valid {
+            return None;
+        }
+
+        Some(CDestinationInstruction {
+            ram: mnemonic.contains('M'),
+            a_register: mnemonic.contains('A'),
+            d_register: mnemonic.contains('D'),
+        })
+    }
+
+    pub fn from_bits(bits: u16) -> CDestinationInstruction {
+        CDestinationInstruction {
+            a_register: bits & 0b100 != 0,
+            d_register: bits & 0b010 != 0,
+            ram: bits & 0b001 != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        (self.a_register as u16) << 2 | (self.d_register as u16) << 1 | self.ram as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits() == 0
+    }
+}
+
+impl fmt::Display for CDestinationInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.a_register {
+            write!(f, "A")?;
+        }
+
+        if self.d_register {
+            write!(f, "D")?;
+        }
+
+        if self.ram {
+            write!(f, "M")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CJumpInstruction {
+    pub fn from_mnemonic(mnemonic: &str) -> Option<CJumpInstruction> {
+        if mnemonic.is_empty() {
+            return None;
+        }
+
+        JUMPS
+            .iter()
+            .position(|jump| *jump == mnemonic)
+            .map(|bits| CJumpInstruction::from_bits(bits as u16))
+    }
+
+    pub fn from_bits(bits: u16) -> CJumpInstruction {
+        CJumpInstruction {
+            lower_than: bits & 0b100 != 0,
+            equal: bits & 0b010 != 0,
+            greater_than: bits & 0b001 != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        (self.lower_than as u16) << 2 | (self.equal as u16) << 1 | self.greater_than as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits() == 0
+    }
+}
+
+impl fmt::Display for CJumpInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", JUMPS[self.bits() as usize])
+    }
+}
+
+impl CCompInstruction {
+    pub fn from_mnemonic(mnemonic: &str) -> Option<CCompInstruction> {
+        COMPUTATIONS
+            .iter()
+            .chain(SHIFTS.iter())
+            .find(|(_, m, _)| *m == mnemonic)
+            .map(|(comp, _, _)| *comp)
+    }
+
+    pub fn from_bits(bits: u16) -> Option<CCompInstruction> {
+        COMPUTATIONS
+            .iter()
+            .find(|(_, _, b)| *b == bits)
+            .map(|(comp, _, _)| *comp)
+    }
+
+    pub fn from_shift_bits(bits: u16) -> Option<CCompInstruction> {
+        SHIFTS
+            .iter()
+            .find(|(_, _, b)| *b == bits)
+            .map(|(comp, _, _)| *comp)
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.entry().2
+    }
+
+    // The three leading bits of a C-instruction computing this
+    pub fn prefix(&self) -> u16 {
+        if self.is_shift() {
+            0b101
+        } else {
+            0b111
+        }
+    }
+
+    pub fn is_shift(&self) -> bool {
+        SHIFTS.iter().any(|(c, _, _)| c == self)
+    }
+
+    // Whether the computation reads RAM[A], i.e. has the `a` bit set
+    pub fn uses_ram(&self) -> bool {
+        self.bits() & 0b100_0000 != 0
+    }
+
+    fn entry(&self) -> &'static (CCompInstruction, &'static str, u16) {
+        COMPUTATIONS
+            .iter()
+            .chain(SHIFTS.iter())
+            .find(|(c, _, _)| c == self)
+            .unwrap()
+    }
+}
+
+impl CInstruction {
+    pub fn from_bits(word: u16) -> Option<CInstruction> {
+        let comp = match word >> 13 {
+            0b111 => CCompInstruction::from_bits(word >> 6 & 0b111_1111)?,
+            0b101 => CCompInstruction::from_shift_bits(word >> 6 & 0b111_1111)?,
+            _ => return None,
+        };
+
+        Some(CInstruction {
+            comp,
+            dest: CDestinationInstruction::from_bits(word >> 3 & 0b111),
+            jump: CJumpInstruction::from_bits(word & 0b111),
+        })
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.comp.prefix() << 13 | self.comp.bits() << 6 | self.dest.bits() << 3 | self.jump.bits()
+    }
+}
+
+impl fmt::Display for CInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.dest.is_empty() {
+            write!(f, "{}=", self.dest)?;
+        }
+
+        write!(f, "{}", self.comp.mnemonic())?;
+
+        if !self.jump.is_empty() {
+            write!(f, ";{}", self.jump)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Instruction<'a> {
+    // Decodes a single 16-bit word of Hack machine code
+    pub fn decode(word: u16) -> Option<Instruction<'a>> {
+        if word & 0b1000_0000_0000_0000 == 0 {
+            Some(Instruction::A(AInstruction::Literal(word)))
+        } else {
+            CInstruction::from_bits(word).map(Instruction::C)
+        }
+    }
+}
+
+impl<'a> fmt::Display for Instruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::A(AInstruction::Literal(address)) => write!(f, "@{}", address),
+            Instruction::A(AInstruction::Mnemonic(name)) => write!(f, "@{}", name),
+            Instruction::A(AInstruction::Expression(expression)) => write!(f, "@{}", expression),
+            Instruction::C(instruction) => write!(f, "{}", instruction),
+            Instruction::Label(instruction) => write!(f, "({})", instruction.name),
+            Instruction::Constant(instruction) => {
+                write!(f, ".equ {} {}", instruction.name, instruction.value)
+            }
+            Instruction::Global(instruction) => write!(f, ".global {}", instruction.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Span<'a> {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub source: &'a str,
+}
+
+impl<'a> Span<'a> {
+    // Builds a span for the `part` which must be a slice of the `source` line
+    pub fn new(line: usize, source: &'a str, part: &str) -> Span<'a> {
+        Span {
+            line,
+            column: part.as_ptr() as usize - source.as_ptr() as usize + 1,
+            length: part.chars().count(),
+            source,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Statement<'a> {
+    pub instruction: Instruction<'a>,
+    pub span: Span<'a>,
 }
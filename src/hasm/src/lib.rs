@@ -1,21 +1,191 @@
 pub mod codegen;
 pub mod config;
+pub mod debuginfo;
+pub mod disasm;
+pub mod error;
+pub mod expr;
+pub mod format;
 pub mod instructions;
+pub mod linker;
+pub mod lint;
+pub mod listing;
+pub mod macros;
+pub mod object;
+pub mod optimize;
 pub mod parser;
 pub mod symbols;
 
-use config::Config;
+use config::{Config, LinkerConfig};
+use error::{Error, Warning};
+use instructions::Isa;
 use std::fs;
+use std::io;
 use std::io::prelude::*;
+use std::process;
+
+const STDIO: &str = "-";
+
+// Assembles the source without touching the filesystem, unless it includes other files
+pub fn assemble(source: &str) -> Result<Vec<u16>, Vec<Error>> {
+    let expansion = macros::expand(source, "<input>")?;
+    let statements =
+        parser::parse(&expansion.code, Isa::Standard).map_err(|errors| expansion.locate(errors))?;
+    let symbol_table = symbols::resolve(&statements).map_err(|errors| expansion.locate(errors))?;
+
+    codegen::codegen(&statements, &symbol_table).map_err(|errors| expansion.locate(errors))
+}
 
 pub fn run(config: Config) {
-    let assembly_code = fs::read_to_string(config.input_file).expect("Could not read input file");
-    let instructions = parser::parse(&assembly_code);
-    let symbol_table = symbols::resolve(&instructions);
-    let machine_code = codegen::codegen(&instructions, &symbol_table);
-
-    let mut out_file = fs::File::create(config.output_file).expect("Could not open output file");
-    out_file
-        .write_all(machine_code.as_bytes())
-        .expect("Could not write to the output file");
+    let sources: Vec<(String, String)> = config
+        .input_files
+        .iter()
+        .map(|file| (display_name(file), read_input(file)))
+        .collect();
+
+    let name = sources
+        .iter()
+        .map(|(file, _)| file.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if config.disassemble {
+        return disassemble(&config, &name, &sources);
+    }
+
+    let expansion = macros::expand_files(&sources).unwrap_or_else(|errors| abort(&name, &errors));
+
+    if config.expand {
+        return write_output(&config.output_file, &expansion.code);
+    }
+
+    let statements = parser::parse(&expansion.code, config.isa)
+        .unwrap_or_else(|errors| abort(&name, &expansion.locate(errors)));
+    if config.warnings {
+        warn(&name, &expansion.locate_warnings(lint::lint(&statements)));
+    }
+
+    let statements = if config.optimize {
+        optimize::optimize(statements)
+    } else {
+        statements
+    };
+
+    if config.object {
+        let object = object::assemble(&statements)
+            .unwrap_or_else(|errors| abort(&name, &expansion.locate(errors)));
+        return write_output(&config.output_file, object::print(&object));
+    }
+
+    let symbol_table = symbols::resolve(&statements)
+        .unwrap_or_else(|errors| abort(&name, &expansion.locate(errors)));
+    let machine_code = codegen::codegen(&statements, &symbol_table)
+        .unwrap_or_else(|errors| abort(&name, &expansion.locate(errors)));
+
+    if let Some(listing_file) = &config.listing_file {
+        let listing = listing::listing(&statements, &symbol_table, &expansion);
+        write_output(listing_file, &listing);
+    }
+
+    if let Some(debug_info_file) = &config.debug_info_file {
+        let debug_info = debuginfo::collect(&statements, &symbol_table, &expansion);
+        write_output(debug_info_file, debuginfo::print(&debug_info));
+    }
+
+    write_output(
+        &config.output_file,
+        format::format(&machine_code, config.format),
+    );
+}
+
+pub fn link(config: LinkerConfig) {
+    let objects: Vec<(String, object::Object)> = config
+        .input_files
+        .iter()
+        .map(|file| {
+            let object = object::parse(&read_input(file))
+                .unwrap_or_else(|errors| abort(&display_name(file), &errors));
+            (display_name(file), object)
+        })
+        .collect();
+
+    let machine_code =
+        linker::link(&objects).unwrap_or_else(|errors| abort(&config.output_file, &errors));
+
+    write_output(
+        &config.output_file,
+        format::format(&machine_code, config.format),
+    );
+}
+
+fn disassemble(config: &Config, name: &str, sources: &[(String, String)]) {
+    let machine_code = sources
+        .iter()
+        .map(|(_, source)| source.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let assembly_code =
+        disasm::disassemble(&machine_code).unwrap_or_else(|errors| abort(name, &errors));
+
+    write_output(&config.output_file, &assembly_code);
+}
+
+fn display_name(file: &str) -> String {
+    if file == STDIO {
+        String::from("<stdin>")
+    } else {
+        String::from(file)
+    }
+}
+
+fn read_input(file: &str) -> String {
+    if file == STDIO {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .expect("Could not read from stdin");
+
+        return source;
+    }
+
+    fs::read_to_string(file).expect("Could not read input file")
+}
+
+fn write_output<C: AsRef<[u8]>>(file: &str, contents: C) {
+    if file == STDIO {
+        return io::stdout()
+            .write_all(contents.as_ref())
+            .expect("Could not write to stdout");
+    }
+
+    fs::write(file, contents).expect("Could not write to the output file");
+}
+
+fn warn(file: &str, warnings: &[Warning]) {
+    for warning in warnings {
+        eprintln!("{}", warning.report(file));
+    }
+
+    if !warnings.is_empty() {
+        eprintln!(
+            "warning: `{}` generated {} warning{}\n",
+            file,
+            warnings.len(),
+            if warnings.len() == 1 { "" } else { "s" }
+        );
+    }
+}
+
+fn abort(file: &str, errors: &[Error]) -> ! {
+    for error in errors {
+        eprintln!("{}", error.report(file));
+    }
+
+    eprintln!(
+        "error: could not assemble `{}` due to {} previous error{}",
+        file,
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" }
+    );
+
+    process::exit(1);
 }
@@ -0,0 +1,92 @@
+use crate::error::*;
+use crate::object::*;
+use std::collections::HashMap;
+
+const ROM_SIZE: usize = 32768;
+const SCREEN: u16 = 16384;
+
+// Links objects in the given order into absolute machine code.
+// Every object is named by the file it was read from, so errors can point at it
+pub fn link(objects: &[(String, Object)]) -> Result<Vec<u16>, Vec<Error>> {
+    let mut errors: Vec<Error> = Vec::new();
+    let mut symbols: HashMap<&str, u16> = HashMap::new();
+    let mut bases: Vec<u16> = Vec::new();
+    let mut rom_size: usize = 0;
+
+    for (file, object) in objects {
+        // Later objects would not fit either
+        if rom_size + object.text.len() > ROM_SIZE {
+            errors.push(Error::in_file(ErrorKind::RomOverflow, file));
+            break;
+        }
+
+        let base = rom_size as u16;
+        for (name, offset) in &object.exports {
+            let address = match rom_address(base, *offset) {
+                Some(address) => address,
+                None => {
+                    errors.push(Error::in_file(ErrorKind::RomOverflow, file));
+                    continue;
+                }
+            };
+
+            if symbols.insert(name, address).is_some() {
+                errors.push(Error::in_file(
+                    ErrorKind::DuplicateSymbol(name.clone()),
+                    file,
+                ));
+            }
+        }
+
+        bases.push(base);
+        rom_size += object.text.len();
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Imports nobody exports are variables, allocated from RAM[16] in the order of appearance
+    let mut next_address: u16 = 16;
+    let mut machine_code: Vec<u16> = Vec::new();
+
+    for ((file, object), base) in objects.iter().zip(bases) {
+        let mut text = object.text.clone();
+
+        for relocation in &object.relocations {
+            match relocation {
+                Relocation::Local(at) => match rom_address(base, text[*at as usize]) {
+                    Some(address) => text[*at as usize] = address,
+                    None => errors.push(Error::in_file(ErrorKind::RomOverflow, file)),
+                },
+                Relocation::Import(at, name) => {
+                    if !symbols.contains_key(name.as_str()) {
+                        if next_address >= SCREEN {
+                            errors.push(Error::in_file(ErrorKind::RamOverflow(name.clone()), file));
+                            continue;
+                        }
+
+                        symbols.insert(name, next_address);
+                        next_address += 1;
+                    }
+
+                    text[*at as usize] = symbols[name.as_str()];
+                }
+            }
+        }
+
+        machine_code.extend(text);
+    }
+
+    if errors.is_empty() {
+        Ok(machine_code)
+    } else {
+        Err(errors)
+    }
+}
+
+// Address of an offset into an object placed at `base`, objects read from files may have any
+fn rom_address(base: u16, offset: u16) -> Option<u16> {
+    base.checked_add(offset)
+        .filter(|address| (*address as usize) < ROM_SIZE)
+}
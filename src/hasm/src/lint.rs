@@ -0,0 +1,71 @@
+use crate::error::*;
+use crate::expr;
+use crate::instructions::*;
+use crate::symbols::is_predefined;
+use std::collections::{HashMap, HashSet};
+
+pub fn lint(statements: &[Statement]) -> Vec<Warning> {
+    let mut warnings: Vec<Warning> = Vec::new();
+    let mut labels: HashSet<&str> = HashSet::new();
+    let mut references: HashMap<&str, usize> = HashMap::new();
+
+    for statement in statements {
+        match &statement.instruction {
+            Instruction::Label(label) if !labels.insert(label.name) => {
+                warnings.push(Warning::new(
+                    WarningKind::DuplicateLabel(String::from(label.name)),
+                    &statement.span,
+                ));
+            }
+            Instruction::Constant(constant) => {
+                labels.insert(constant.name);
+                for name in expr::symbols(constant.value) {
+                    *references.entry(name).or_insert(0) += 1;
+                }
+            }
+            // Exported labels are used by other objects
+            Instruction::A(AInstruction::Mnemonic(name))
+            | Instruction::Global(GlobalInstruction { name }) => {
+                *references.entry(name).or_insert(0) += 1;
+            }
+            Instruction::A(AInstruction::Expression(expression)) => {
+                for name in expr::symbols(expression) {
+                    *references.entry(name).or_insert(0) += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for statement in statements {
+        match &statement.instruction {
+            Instruction::Label(label) if !references.contains_key(label.name) => {
+                warnings.push(Warning::new(
+                    WarningKind::UnusedLabel(String::from(label.name)),
+                    &statement.span,
+                ));
+            }
+            Instruction::A(AInstruction::Mnemonic(name))
+                if references[name] == 1 && !labels.contains(name) && !is_predefined(name) =>
+            {
+                warnings.push(Warning::new(
+                    WarningKind::SingleUseSymbol(String::from(*name)),
+                    &statement.span,
+                ));
+            }
+            Instruction::C(instruction)
+                if instruction.dest.a_register && !instruction.jump.is_empty() =>
+            {
+                warnings.push(Warning::new(
+                    WarningKind::JumpWithADestination,
+                    &statement.span,
+                ));
+            }
+            _ => (),
+        }
+    }
+
+    // Duplicate labels are found in a separate pass, keep warnings in the order of lines
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
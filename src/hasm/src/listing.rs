@@ -0,0 +1,128 @@
+use crate::codegen::encode;
+use crate::expr;
+use crate::instructions::*;
+use crate::macros::Expansion;
+use crate::symbols::is_predefined;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+#[allow(clippy::implicit_hasher)]
+pub fn listing(
+    statements: &[Statement],
+    symbol_table: &HashMap<&str, u16>,
+    expansion: &Expansion,
+) -> String {
+    let mut listing = String::new();
+    let mut ip: u16 = 0;
+
+    listing.push_str(&format!(
+        "{:<5}  {:<16}  {:<24}  {}\n",
+        "ROM", "ENCODING", "LOCATION", "SOURCE"
+    ));
+
+    for statement in statements {
+        let location = match expansion.origin(statement.span.line) {
+            Some(origin) => format!("{}:{}", file_name(&origin.file), origin.line),
+            None => statement.span.line.to_string(),
+        };
+
+        let (address, encoding, symbol) = match &statement.instruction {
+            Instruction::Label(label) => {
+                let address = symbol_table.get(label.name).unwrap_or(&label.ptr);
+                (
+                    String::new(),
+                    String::new(),
+                    format!("{} = {}", label.name, address),
+                )
+            }
+            Instruction::Constant(constant) => (
+                String::new(),
+                String::new(),
+                format!("{} = {}", constant.name, symbol_table[constant.name]),
+            ),
+            Instruction::Global(_) => (String::new(), String::new(), String::new()),
+            instruction => {
+                let word = encode(instruction, symbol_table).unwrap_or_default();
+                let symbol = match instruction {
+                    Instruction::A(AInstruction::Mnemonic(name))
+                    | Instruction::A(AInstruction::Expression(name)) => {
+                        format!("{} = {}", name, word)
+                    }
+                    _ => String::new(),
+                };
+
+                ip += 1;
+                (format!("{:05}", ip - 1), format!("{:016b}", word), symbol)
+            }
+        };
+
+        let line = format!(
+            "{:<5}  {:<16}  {:<24}  {:<40}  {}",
+            address,
+            encoding,
+            location,
+            statement.span.source.trim(),
+            symbol
+        );
+
+        listing.push_str(line.trim_end());
+        listing.push('\n');
+    }
+
+    listing.push_str(&symbols_dump(statements, symbol_table));
+    listing
+}
+
+fn symbols_dump(statements: &[Statement], symbol_table: &HashMap<&str, u16>) -> String {
+    let mut labels: BTreeSet<(u16, &str)> = BTreeSet::new();
+    let mut constants: BTreeSet<(u16, &str)> = BTreeSet::new();
+    let mut variables: BTreeSet<(u16, &str)> = BTreeSet::new();
+
+    for statement in statements {
+        match &statement.instruction {
+            Instruction::Label(label) => {
+                labels.insert((symbol_table[label.name], label.name));
+            }
+            Instruction::Constant(constant) => {
+                constants.insert((symbol_table[constant.name], constant.name));
+            }
+            _ => (),
+        }
+    }
+
+    for statement in statements {
+        let names = match &statement.instruction {
+            Instruction::A(AInstruction::Mnemonic(name)) => vec![*name],
+            Instruction::A(AInstruction::Expression(expression)) => expr::symbols(expression),
+            _ => continue,
+        };
+
+        for name in names {
+            let symbol = (symbol_table[name], name);
+            if !is_predefined(name) && !labels.contains(&symbol) && !constants.contains(&symbol) {
+                variables.insert(symbol);
+            }
+        }
+    }
+
+    let mut dump = String::new();
+    for (title, symbols) in [
+        ("LABELS", labels),
+        ("CONSTANTS", constants),
+        ("VARIABLES", variables),
+    ] {
+        dump.push_str(&format!("\n{}\n", title));
+        for (address, name) in symbols {
+            dump.push_str(&format!("{:05}  {}\n", address, name));
+        }
+    }
+
+    dump
+}
+
+fn file_name(file: &str) -> &str {
+    Path::new(file)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file)
+}
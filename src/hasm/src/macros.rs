@@ -0,0 +1,367 @@
+use crate::error::*;
+use crate::instructions::Span;
+use crate::parser::is_symbol;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub file: String,
+    pub line: usize,
+}
+
+// Plain Hack assembly with every macro and include expanded.
+// Each line of the `code` remembers the line of the original file it came from
+#[derive(Debug, Default)]
+pub struct Expansion {
+    pub code: String,
+    pub origins: Vec<Origin>,
+}
+
+impl Expansion {
+    pub fn origin(&self, line: usize) -> Option<&Origin> {
+        self.origins.get(line.wrapping_sub(1))
+    }
+
+    // Moves errors reported against the expanded code back to the files they came from
+    pub fn locate(&self, errors: Vec<Error>) -> Vec<Error> {
+        errors
+            .into_iter()
+            .map(|error| match self.origin(error.line) {
+                Some(origin) => Error {
+                    file: Some(origin.file.clone()),
+                    line: origin.line,
+                    ..error
+                },
+                None => error,
+            })
+            .collect()
+    }
+
+    pub fn locate_warnings(&self, warnings: Vec<Warning>) -> Vec<Warning> {
+        warnings
+            .into_iter()
+            .map(|warning| match self.origin(warning.line) {
+                Some(origin) => Warning {
+                    file: Some(origin.file.clone()),
+                    line: origin.line,
+                    ..warning
+                },
+                None => warning,
+            })
+            .collect()
+    }
+
+    fn push(&mut self, line: &str, origin: &Origin) {
+        self.code.push_str(line);
+        self.code.push('\n');
+        self.origins.push(origin.clone());
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<(String, Origin)>,
+}
+
+struct Expander {
+    macros: HashMap<String, Rc<Macro>>,
+    includes: Vec<PathBuf>,
+    expansion: Expansion,
+    errors: Vec<Error>,
+    expansions_count: usize,
+}
+
+pub fn expand(source: &str, file: &str) -> Result<Expansion, Vec<Error>> {
+    expand_files(&[(String::from(file), String::from(source))])
+}
+
+// Expands several files as if they were concatenated, in the given order
+pub fn expand_files(sources: &[(String, String)]) -> Result<Expansion, Vec<Error>> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        includes: Vec::new(),
+        expansion: Expansion::default(),
+        errors: Vec::new(),
+        expansions_count: 0,
+    };
+
+    for (file, source) in sources {
+        expander.includes.push(canonical(Path::new(file)));
+        expander.expand_lines(&lines_of(source, file), 0);
+        expander.includes.pop();
+    }
+
+    if expander.errors.is_empty() {
+        Ok(expander.expansion)
+    } else {
+        Err(expander.errors)
+    }
+}
+
+fn lines_of(source: &str, file: &str) -> Vec<(String, Origin)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let origin = Origin {
+                file: String::from(file),
+                line: index + 1,
+            };
+
+            (String::from(line), origin)
+        })
+        .collect()
+}
+
+// The line without its `//` comment. Include paths have single slashes, so those are kept
+fn code_of(line: &str) -> &str {
+    line.split("//").next().unwrap().trim()
+}
+
+// The same file may be reached through different paths, like `a.asm` and `./a.asm`
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn error(kind: ErrorKind, origin: &Origin, line: &str, part: &str) -> Error {
+    Error {
+        file: Some(origin.file.clone()),
+        ..Error::new(kind, &Span::new(origin.line, line, part))
+    }
+}
+
+impl Expander {
+    fn expand_lines(&mut self, lines: &[(String, Origin)], depth: usize) {
+        let mut index = 0;
+
+        while index < lines.len() {
+            let (line, origin) = &lines[index];
+            let code = code_of(line);
+            let mut words = code.splitn(2, char::is_whitespace);
+            let keyword = words.next().unwrap_or("");
+            let rest = words.next().unwrap_or("").trim();
+
+            match keyword {
+                ".macro" => index = self.define(lines, index, rest),
+                ".endm" => self.errors.push(error(
+                    ErrorKind::UnexpectedDirective(String::from(keyword)),
+                    origin,
+                    line,
+                    keyword,
+                )),
+                ".include" => self.include(line, origin, rest, depth),
+                _ => match self.macros.get(keyword).cloned() {
+                    Some(definition) => {
+                        self.invoke(keyword, &definition, line, origin, rest, depth)
+                    }
+                    None => self.expansion.push(line, origin),
+                },
+            };
+
+            index += 1;
+        }
+    }
+
+    // Collects the macro body up to `.endm` and returns the index of the `.endm` line
+    fn define(&mut self, lines: &[(String, Origin)], start: usize, header: &str) -> usize {
+        let (line, origin) = &lines[start];
+        let mut words = header
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty());
+
+        let name = words.next().unwrap_or("");
+        let params: Vec<String> = words.map(String::from).collect();
+        if !is_symbol(name) || params.iter().any(|param| !is_symbol(param)) {
+            let code = code_of(line);
+            self.errors.push(error(
+                ErrorKind::InvalidDirective(String::from(code)),
+                origin,
+                line,
+                code,
+            ));
+        }
+
+        let mut body = Vec::new();
+        for (index, (body_line, body_origin)) in lines.iter().enumerate().skip(start + 1) {
+            let keyword = code_of(body_line).split_whitespace().next();
+
+            match keyword {
+                Some(".endm") => {
+                    if self.macros.contains_key(name) {
+                        self.errors.push(error(
+                            ErrorKind::MacroRedefinition(String::from(name)),
+                            origin,
+                            line,
+                            name,
+                        ));
+                    }
+
+                    let definition = Macro { params, body };
+                    self.macros.insert(String::from(name), Rc::new(definition));
+                    return index;
+                }
+                Some(".macro") => {
+                    self.errors.push(error(
+                        ErrorKind::UnexpectedDirective(String::from(".macro")),
+                        body_origin,
+                        body_line,
+                        body_line.trim(),
+                    ));
+                }
+                _ => body.push((body_line.clone(), body_origin.clone())),
+            }
+        }
+
+        self.errors.push(error(
+            ErrorKind::UnterminatedMacro(String::from(name)),
+            origin,
+            line,
+            line.trim(),
+        ));
+
+        lines.len()
+    }
+
+    fn include(&mut self, line: &str, origin: &Origin, argument: &str, depth: usize) {
+        let is_quoted = argument.len() > 2 && argument.starts_with('"') && argument.ends_with('"');
+        if !is_quoted {
+            let code = code_of(line);
+            self.errors.push(error(
+                ErrorKind::InvalidDirective(String::from(code)),
+                origin,
+                line,
+                code,
+            ));
+
+            return;
+        }
+
+        let name = &argument[1..argument.len() - 1];
+        let path = Path::new(&origin.file)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(name);
+
+        if self.includes.contains(&canonical(&path)) {
+            self.errors.push(error(
+                ErrorKind::IncludeCycle(String::from(name)),
+                origin,
+                line,
+                argument,
+            ));
+
+            return;
+        }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(_) => {
+                self.errors.push(error(
+                    ErrorKind::IncludeNotFound(path.display().to_string()),
+                    origin,
+                    line,
+                    argument,
+                ));
+
+                return;
+            }
+        };
+
+        self.includes.push(canonical(&path));
+        self.expand_lines(&lines_of(&source, &path.display().to_string()), depth);
+        self.includes.pop();
+    }
+
+    fn invoke(
+        &mut self,
+        name: &str,
+        definition: &Macro,
+        line: &str,
+        origin: &Origin,
+        arguments: &str,
+        depth: usize,
+    ) {
+        if depth >= MAX_DEPTH {
+            self.errors.push(error(
+                ErrorKind::MacroRecursion(String::from(name)),
+                origin,
+                line,
+                name,
+            ));
+
+            return;
+        }
+
+        let arguments: Vec<&str> = if arguments.is_empty() {
+            Vec::new()
+        } else {
+            arguments.split(',').map(str::trim).collect()
+        };
+
+        if arguments.len() != definition.params.len() {
+            self.errors.push(error(
+                ErrorKind::MacroArity(String::from(name), definition.params.len(), arguments.len()),
+                origin,
+                line,
+                code_of(line),
+            ));
+
+            return;
+        }
+
+        self.expansions_count += 1;
+
+        let mut substitutions: HashMap<&str, String> = HashMap::new();
+        for (param, argument) in definition.params.iter().zip(&arguments) {
+            substitutions.insert(param, String::from(*argument));
+        }
+
+        // Labels declared inside the macro body are made unique for every expansion
+        for (body_line, _) in &definition.body {
+            let code = code_of(body_line);
+            if code.starts_with('(') && code.ends_with(')') && code.len() > 2 {
+                let label = &code[1..code.len() - 1];
+                substitutions.insert(label, format!("{}${}", label, self.expansions_count));
+            }
+        }
+
+        // Expanded lines are attributed to the line where the macro was invoked
+        let lines: Vec<(String, Origin)> = definition
+            .body
+            .iter()
+            .map(|(body_line, _)| (substitute(body_line, &substitutions), origin.clone()))
+            .collect();
+
+        self.expand_lines(&lines, depth + 1);
+    }
+}
+
+// Replaces whole symbols in the code part of the line, leaving comments untouched
+fn substitute(line: &str, substitutions: &HashMap<&str, String>) -> String {
+    let (code, comment) = match line.find("//") {
+        Some(index) => line.split_at(index),
+        None => (line, ""),
+    };
+
+    let mut result = String::new();
+    let mut symbol = String::new();
+    for c in code.chars() {
+        if c.is_ascii_alphanumeric() || "_.$:".contains(c) {
+            symbol.push(c);
+            continue;
+        }
+
+        result.push_str(substitutions.get(symbol.as_str()).unwrap_or(&symbol));
+        result.push(c);
+        symbol.clear();
+    }
+
+    result.push_str(substitutions.get(symbol.as_str()).unwrap_or(&symbol));
+    result.push_str(comment);
+    result
+}
@@ -0,0 +1,217 @@
+use crate::error::*;
+use crate::expr;
+use crate::instructions::*;
+use crate::symbols::definitions;
+use std::collections::HashMap;
+
+// An A-instruction whose address is not known until link time
+#[derive(Debug, Clone, PartialEq)]
+pub enum Relocation {
+    // Refers to a label of the same object, the object base address is added to it
+    Local(u16),
+    // Refers to a label exported by another object or to a variable allocated by the linker
+    Import(u16, String),
+}
+
+// Relocatable object: code of the `.text` section assembled as if it started at ROM[0],
+// labels it exports with their offsets and the instructions the linker has to patch.
+// Only labels named by `.global` are exported, the others are local to the object
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Object {
+    pub text: Vec<u16>,
+    pub exports: Vec<(String, u16)>,
+    pub relocations: Vec<Relocation>,
+}
+
+pub fn assemble(statements: &[Statement]) -> Result<Object, Vec<Error>> {
+    let mut object = Object::default();
+    let mut errors: Vec<Error> = Vec::new();
+    let mut labels: HashMap<&str, u16> = HashMap::new();
+
+    // Symbols are evaluated as if the object was placed at ROM[0] and at ROM[1],
+    // the ones moving along with the object need a relocation
+    let absolute = definitions(statements, 0)?;
+    let moved = definitions(statements, 1)?;
+
+    for statement in statements {
+        if let Instruction::Label(label) = &statement.instruction {
+            labels.insert(label.name, label.ptr);
+        }
+    }
+
+    for statement in statements {
+        if let Instruction::Global(global) = &statement.instruction {
+            match labels.get(global.name) {
+                Some(_) if object.exports.iter().any(|(name, _)| name == global.name) => (),
+                Some(offset) => object.exports.push((String::from(global.name), *offset)),
+                None => errors.push(Error::new(
+                    ErrorKind::UndefinedGlobal(String::from(global.name)),
+                    &Span::new(statement.span.line, statement.span.source, global.name),
+                )),
+            }
+        }
+    }
+
+    for statement in statements {
+        let offset = object.text.len() as u16;
+
+        match &statement.instruction {
+            Instruction::A(AInstruction::Literal(address)) => object.text.push(*address),
+            Instruction::A(AInstruction::Mnemonic(name)) => {
+                match (absolute.get(name), moved.get(name)) {
+                    (Some(address), Some(_)) if *address > 32767 => {
+                        object.text.push(0);
+                        errors.push(Error::new(
+                            ErrorKind::AddressOverflow(
+                                String::from(*name),
+                                i64::from(*address as i16),
+                            ),
+                            &Span::new(statement.span.line, statement.span.source, name),
+                        ));
+                    }
+                    (Some(address), Some(moved)) => {
+                        object.text.push(*address);
+                        if address != moved {
+                            object.relocations.push(Relocation::Local(offset));
+                        }
+                    }
+                    _ => {
+                        object.text.push(0);
+                        object
+                            .relocations
+                            .push(Relocation::Import(offset, String::from(*name)));
+                    }
+                }
+            }
+            Instruction::A(AInstruction::Expression(expression)) => {
+                let value = |symbols: &HashMap<&str, u16>| {
+                    expr::evaluate(expression, |name| symbols.get(name).copied())
+                };
+                let address =
+                    expr::evaluate_address(expression, |name| absolute.get(name).copied());
+
+                let kind = match (address, value(&moved)) {
+                    (Ok(address), Ok(moved)) if address == moved => {
+                        object.text.push(address);
+                        continue;
+                    }
+                    (Ok(address), Ok(moved)) if address.wrapping_add(1) == moved => {
+                        object.text.push(address);
+                        object.relocations.push(Relocation::Local(offset));
+                        continue;
+                    }
+                    (Err(ErrorKind::UnresolvedSymbol(_)), _) | (Ok(_), Ok(_)) => {
+                        ErrorKind::UnrelocatableExpression(String::from(*expression))
+                    }
+                    (Err(kind), _) | (_, Err(kind)) => kind,
+                };
+
+                object.text.push(0);
+                errors.push(Error::new(
+                    kind,
+                    &Span::new(statement.span.line, statement.span.source, expression),
+                ));
+            }
+            Instruction::C(instruction) => object.text.push(instruction.bits()),
+            Instruction::Label(_) | Instruction::Constant(_) | Instruction::Global(_) => (),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(object)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn print(object: &Object) -> String {
+    let mut output = String::from("// Hack relocatable object\n");
+
+    output.push_str(&format!(".text {}\n", object.text.len()));
+    for word in &object.text {
+        output.push_str(&format!("{:016b}\n", word));
+    }
+
+    for (name, offset) in &object.exports {
+        output.push_str(&format!(".export {} {}\n", name, offset));
+    }
+
+    for relocation in &object.relocations {
+        match relocation {
+            Relocation::Local(offset) => output.push_str(&format!(".reloc {}\n", offset)),
+            Relocation::Import(offset, name) => {
+                output.push_str(&format!(".reloc {} {}\n", offset, name))
+            }
+        }
+    }
+
+    output
+}
+
+pub fn parse(source: &str) -> Result<Object, Vec<Error>> {
+    let mut object = Object::default();
+    let mut errors: Vec<Error> = Vec::new();
+    let mut text_size: Option<usize> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split('/').next().unwrap().trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        let words: Vec<&str> = code.split_whitespace().collect();
+        let offset = |word: &str| word.parse::<u16>().ok();
+        let parsed = match words.as_slice() {
+            [".text", size] => size.parse().ok().map(|size| text_size = Some(size)),
+            [".export", name, ptr] => offset(ptr).map(|ptr| {
+                object.exports.push((String::from(*name), ptr));
+            }),
+            [".reloc", at] => offset(at).map(|at| {
+                object.relocations.push(Relocation::Local(at));
+            }),
+            [".reloc", at, name] => offset(at).map(|at| {
+                object
+                    .relocations
+                    .push(Relocation::Import(at, String::from(*name)));
+            }),
+            [word] if text_size.is_some() && is_binary(word) => u16::from_str_radix(word, 2)
+                .ok()
+                .map(|word| object.text.push(word)),
+            _ => None,
+        };
+
+        if parsed.is_none() {
+            errors.push(Error::new(
+                ErrorKind::InvalidObject(String::from(code)),
+                &Span::new(index + 1, line, code),
+            ));
+        }
+    }
+
+    let is_consistent = text_size == Some(object.text.len())
+        && object
+            .relocations
+            .iter()
+            .all(|relocation| match relocation {
+                Relocation::Local(at) | Relocation::Import(at, _) => {
+                    (*at as usize) < object.text.len()
+                }
+            });
+
+    if !is_consistent && errors.is_empty() {
+        errors.push(Error::new(
+            ErrorKind::CorruptedObject,
+            &Span::new(0, "", ""),
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(object)
+    } else {
+        Err(errors)
+    }
+}
+
+fn is_binary(word: &str) -> bool {
+    word.len() == 16 && word.chars().all(|c| c == '0' || c == '1')
+}
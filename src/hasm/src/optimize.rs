@@ -0,0 +1,294 @@
+use crate::expr;
+use crate::instructions::*;
+use std::collections::HashSet;
+
+// What is known about the A register at some point of straight-line code
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Known<'a> {
+    // A holds the address loaded by `@X`
+    Value(AInstruction<'a>),
+    // A holds RAM[X], e.g. after `@X` and `A=M`
+    Pointer(AInstruction<'a>),
+}
+
+// Peephole optimizations over parsed statements, repeated until nothing changes.
+// Labels are join points, nothing is assumed about registers after them.
+// Pointers are only tracked for SP, LCL, ARG, THIS and THAT, which never point at themselves,
+// so writing through them does not change the pointer
+pub fn optimize(mut statements: Vec<Statement<'_>>) -> Vec<Statement<'_>> {
+    // `@LOOP+2` points into the code by offset, removing anything would move its target
+    if has_label_arithmetic(&statements) {
+        return statements;
+    }
+
+    loop {
+        let mut changed = remove_redundant_loads(&mut statements);
+        changed |= fold_round_trips(&mut statements);
+        changed |= remove_jumps_to_next(&mut statements);
+        changed |= remove_dead_code(&mut statements);
+
+        if !changed {
+            break;
+        }
+    }
+
+    // Removed instructions moved the labels, point them at their new addresses
+    let mut ip: u16 = 0;
+    for statement in &mut statements {
+        match &mut statement.instruction {
+            Instruction::Label(label) => label.ptr = ip,
+            Instruction::A(_) | Instruction::C(_) => ip += 1,
+            Instruction::Constant(_) | Instruction::Global(_) => (),
+        }
+    }
+
+    statements
+}
+
+// `@X` when A already holds X, and `@X; A=M` when A already holds RAM[X]
+fn remove_redundant_loads(statements: &mut Vec<Statement>) -> bool {
+    let mut redundant = vec![false; statements.len()];
+    let mut known: Option<Known> = None;
+    let mut index = 0;
+
+    while index < statements.len() {
+        match &statements[index].instruction {
+            Instruction::A(address) if known == Some(Known::Value(*address)) => {
+                redundant[index] = true;
+            }
+            Instruction::A(address)
+                if known == Some(Known::Pointer(*address))
+                    && is(statements.get(index + 1), "A=M") =>
+            {
+                redundant[index] = true;
+                redundant[index + 1] = true;
+                index += 1;
+            }
+            // `D=M; @SP; A=M; M=D` stores the value back where it was just read from
+            Instruction::C(instruction)
+                if known == Some(Known::Pointer(AInstruction::Mnemonic("SP")))
+                    && instruction.to_string() == "D=M"
+                    && is_load(statements.get(index + 1), "SP")
+                    && is(statements.get(index + 2), "A=M")
+                    && is(statements.get(index + 3), "M=D") =>
+            {
+                redundant[index + 1] = true;
+                redundant[index + 2] = true;
+                redundant[index + 3] = true;
+                index += 3;
+            }
+            Instruction::A(address) => known = Some(Known::Value(*address)),
+            Instruction::C(instruction) => known = after(known, instruction),
+            Instruction::Label(_) => known = None,
+            Instruction::Constant(_) | Instruction::Global(_) => (),
+        }
+
+        index += 1;
+    }
+
+    remove(statements, &redundant)
+}
+
+fn after<'a>(known: Option<Known<'a>>, instruction: &CInstruction) -> Option<Known<'a>> {
+    if is_unconditional(instruction) {
+        return None;
+    }
+
+    if !instruction.dest.a_register {
+        return known;
+    }
+
+    match known {
+        Some(Known::Value(address))
+            if is_pointer(&address)
+                && (instruction.dest.ram || instruction.comp == CCompInstruction::RAM) =>
+        {
+            Some(Known::Pointer(address))
+        }
+        _ => None,
+    }
+}
+
+// Stores of a value just loaded, loads of a value just stored and increments undone right away
+fn fold_round_trips(statements: &mut Vec<Statement>) -> bool {
+    let mut redundant = vec![false; statements.len()];
+    let mut index = 0;
+
+    while index + 1 < statements.len() {
+        // Popping into D and pushing it right back only reads the top of the stack
+        if is_load(statements.get(index), "SP")
+            && is(statements.get(index + 1), "AM=M-1")
+            && is(statements.get(index + 2), "D=M")
+            && is_load(statements.get(index + 3), "SP")
+            && is(statements.get(index + 4), "M=M+1")
+        {
+            statements[index + 1].instruction = Instruction::C(CInstruction {
+                dest: CDestinationInstruction::from_mnemonic("A").unwrap(),
+                comp: CCompInstruction::RAMMinusOne,
+                jump: CJumpInstruction::from_bits(0),
+            });
+            redundant[index + 3] = true;
+            redundant[index + 4] = true;
+            index += 5;
+            continue;
+        }
+
+        let pair = (
+            mnemonic(&statements[index]),
+            mnemonic(&statements[index + 1]),
+        );
+
+        match pair {
+            (Some("M=D"), Some("D=M")) | (Some("D=M"), Some("M=D")) => {
+                redundant[index + 1] = true;
+            }
+            (Some("M=M+1"), Some("M=M-1")) | (Some("M=M-1"), Some("M=M+1")) => {
+                redundant[index] = true;
+                redundant[index + 1] = true;
+            }
+            (Some("M=M+1"), Some("AM=M-1")) | (Some("M=M-1"), Some("AM=M+1")) => {
+                redundant[index] = true;
+                statements[index + 1].instruction = Instruction::C(CInstruction {
+                    dest: CDestinationInstruction::from_mnemonic("A").unwrap(),
+                    comp: CCompInstruction::RAM,
+                    jump: CJumpInstruction::from_bits(0),
+                });
+            }
+            _ => {
+                index += 1;
+                continue;
+            }
+        }
+
+        index += 2;
+    }
+
+    remove(statements, &redundant)
+}
+
+// `@L; 0;JMP` right before `(L)`, as long as the code after the label loads A first
+fn remove_jumps_to_next(statements: &mut Vec<Statement>) -> bool {
+    let mut redundant = vec![false; statements.len()];
+
+    for index in 0..statements.len().saturating_sub(1) {
+        let target = match (
+            &statements[index].instruction,
+            &statements[index + 1].instruction,
+        ) {
+            (Instruction::A(AInstruction::Mnemonic(target)), Instruction::C(jump))
+                if !jump.jump.is_empty() && jump.dest.is_empty() =>
+            {
+                *target
+            }
+            _ => continue,
+        };
+
+        let rest = &statements[index + 2..];
+        let labels = rest
+            .iter()
+            .take_while(|statement| !is_code(statement))
+            .filter_map(|statement| match &statement.instruction {
+                Instruction::Label(label) => Some(label.name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let next = rest.iter().find(|statement| is_code(statement));
+        let loads_a = match next {
+            Some(statement) => matches!(statement.instruction, Instruction::A(_)),
+            None => true,
+        };
+
+        if labels.contains(&target) && loads_a {
+            redundant[index] = true;
+            redundant[index + 1] = true;
+        }
+    }
+
+    remove(statements, &redundant)
+}
+
+// Instructions after an unconditional jump are unreachable until the next label
+fn remove_dead_code(statements: &mut Vec<Statement>) -> bool {
+    let mut redundant = vec![false; statements.len()];
+    let mut is_reachable = true;
+
+    for (index, statement) in statements.iter().enumerate() {
+        match &statement.instruction {
+            Instruction::Label(_) => is_reachable = true,
+            Instruction::A(_) | Instruction::C(_) if !is_reachable => redundant[index] = true,
+            Instruction::C(instruction) if is_unconditional(instruction) => is_reachable = false,
+            _ => (),
+        }
+    }
+
+    remove(statements, &redundant)
+}
+
+fn has_label_arithmetic(statements: &[Statement]) -> bool {
+    let labels = statements
+        .iter()
+        .filter_map(|statement| match &statement.instruction {
+            Instruction::Label(label) => Some(label.name),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    statements.iter().any(|statement| {
+        let expression = match &statement.instruction {
+            Instruction::A(AInstruction::Expression(expression)) => expression,
+            Instruction::Constant(constant) => constant.value,
+            _ => return false,
+        };
+
+        expr::symbols(expression)
+            .iter()
+            .any(|name| labels.contains(name))
+    })
+}
+
+fn remove(statements: &mut Vec<Statement>, redundant: &[bool]) -> bool {
+    let mut flags = redundant.iter();
+    statements.retain(|_| !flags.next().unwrap());
+
+    redundant.contains(&true)
+}
+
+fn mnemonic(statement: &Statement) -> Option<&'static str> {
+    match &statement.instruction {
+        Instruction::C(instruction) => ["M=D", "D=M", "M=M+1", "M=M-1", "AM=M-1", "AM=M+1"]
+            .iter()
+            .find(|mnemonic| instruction.to_string() == **mnemonic)
+            .copied(),
+        _ => None,
+    }
+}
+
+fn is(statement: Option<&Statement>, expected: &str) -> bool {
+    match statement.map(|statement| &statement.instruction) {
+        Some(Instruction::C(instruction)) => instruction.to_string() == expected,
+        _ => false,
+    }
+}
+
+fn is_load(statement: Option<&Statement>, name: &str) -> bool {
+    matches!(
+        statement.map(|statement| &statement.instruction),
+        Some(Instruction::A(AInstruction::Mnemonic(mnemonic))) if *mnemonic == name
+    )
+}
+
+fn is_pointer(address: &AInstruction) -> bool {
+    matches!(
+        address,
+        AInstruction::Mnemonic("SP" | "LCL" | "ARG" | "THIS" | "THAT")
+    )
+}
+
+fn is_code(statement: &Statement) -> bool {
+    matches!(statement.instruction, Instruction::A(_) | Instruction::C(_))
+}
+
+fn is_unconditional(instruction: &CInstruction) -> bool {
+    instruction.jump.bits() == 0b111
+}
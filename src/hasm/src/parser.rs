@@ -1,143 +1,238 @@
+use crate::error::*;
+use crate::expr;
 use crate::instructions::*;
 
-pub fn parse(source: &str) -> Vec<Instruction> {
-    let mut instructions: Vec<Instruction> = Vec::new();
-    let mut ip: u16 = 0;
+const ROM_SIZE: usize = 32768;
 
-    for instruction in source.lines() {
-        let instruction = instruction.trim().splitn(2, '/').next().unwrap().trim();
+pub fn parse(source: &str, isa: Isa) -> Result<Vec<Statement<'_>>, Vec<Error>> {
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
+    let mut ip: usize = 0;
+
+    for (index, line) in source.lines().enumerate() {
+        let instruction = line.split('/').next().unwrap().trim();
         if instruction.is_empty() {
             continue;
         };
 
-        if instruction.starts_with('@') {
-            let mnemonic = &instruction[1..];
-            match mnemonic.parse::<u16>() {
-                Ok(address) => instructions.push(Instruction::A(AInstruction::Literal(address))),
-                Err(_) => instructions.push(Instruction::A(AInstruction::Mnemonic(mnemonic))),
-            };
-
+        let span = Span::new(index + 1, line, instruction);
+        let instruction = if instruction.starts_with(".equ") || instruction.starts_with(".define") {
+            parse_constant(instruction, &span)
+        } else if instruction.starts_with(".global") {
+            parse_global(instruction, &span)
+        } else if instruction.starts_with('(') {
+            parse_label(instruction, ip, &span)
+        } else if ip == ROM_SIZE {
+            // Only the first instruction that does not fit is reported, the rest are still checked
             ip += 1;
-            continue;
+            Err(Error::new(ErrorKind::RomOverflow, &span))
+        } else if instruction.starts_with('@') {
+            ip += 1;
+            parse_a_instruction(instruction, &span)
+        } else {
+            ip += 1;
+            parse_c_instruction(instruction, isa, &span)
         };
 
-        if instruction.starts_with('(') {
-            let mnemonic = instruction.trim_start_matches('(').trim_end_matches(')');
-            instructions.push(Instruction::Label(LabelInstruction {
-                name: mnemonic,
-                ptr: ip,
-            }));
+        match instruction {
+            Ok(instruction) => statements.push(Statement { instruction, span }),
+            Err(error) => errors.push(error),
+        }
+    }
 
-            continue;
+    if errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || "_.$:".contains(c) => {
+            chars.all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
         }
+        _ => false,
+    }
+}
 
-        let dest = if instruction.contains('=') {
-            let mnemonic = instruction.split('=').nth(0).unwrap();
+fn parse_a_instruction<'a>(instruction: &'a str, span: &Span) -> Result<Instruction<'a>, Error> {
+    let mnemonic = &instruction[1..];
+    let is_number = !mnemonic.is_empty() && mnemonic.chars().all(|c| c.is_ascii_digit());
 
-            CDestinationInstruction {
-                ram: mnemonic.contains('M'),
-                a_register: mnemonic.contains('A'),
-                d_register: mnemonic.contains('D'),
-            }
-        } else {
-            CDestinationInstruction {
-                ram: false,
-                a_register: false,
-                d_register: false,
+    if is_number {
+        let kind = match mnemonic.parse::<i64>() {
+            Ok(address) if address <= 32767 => {
+                return Ok(Instruction::A(AInstruction::Literal(address as u16)))
             }
+            Ok(address) => ErrorKind::AddressOverflow(String::from(mnemonic), address),
+            Err(_) => ErrorKind::InvalidAddress(String::from(mnemonic)),
         };
 
-        let jump = if instruction.contains(';') {
-            let mnemonic = instruction.split(';').nth(1).unwrap();
-            match mnemonic {
-                "JGT" => CJumpInstruction {
-                    greater_than: true,
-                    equal: false,
-                    lower_than: false,
-                },
-                "JEQ" => CJumpInstruction {
-                    greater_than: false,
-                    equal: true,
-                    lower_than: false,
-                },
-                "JGE" => CJumpInstruction {
-                    greater_than: true,
-                    equal: true,
-                    lower_than: false,
-                },
-                "JLT" => CJumpInstruction {
-                    greater_than: false,
-                    equal: false,
-                    lower_than: true,
-                },
-                "JNE" => CJumpInstruction {
-                    greater_than: true,
-                    equal: false,
-                    lower_than: true,
-                },
-                "JLE" => CJumpInstruction {
-                    greater_than: false,
-                    equal: true,
-                    lower_than: true,
-                },
-                "JMP" => CJumpInstruction {
-                    greater_than: true,
-                    equal: true,
-                    lower_than: true,
-                },
-                _ => panic!("Unknown destination instruction: {}", mnemonic),
-            }
-        } else {
-            CJumpInstruction {
-                greater_than: false,
-                equal: false,
-                lower_than: false,
-            }
-        };
+        return Err(Error::new(
+            kind,
+            &Span::new(span.line, span.source, mnemonic),
+        ));
+    }
 
-        let mnemonic = if instruction.contains('=') {
-            instruction.split('=').nth(1).unwrap()
-        } else if instruction.contains(';') {
-            instruction.split(';').nth(0).unwrap()
-        } else {
-            instruction
-        };
+    if is_symbol(mnemonic) {
+        return Ok(Instruction::A(AInstruction::Mnemonic(mnemonic)));
+    }
 
-        let comp = match mnemonic {
-            "0" => CCompInstruction::Zero,
-            "1" => CCompInstruction::One,
-            "-1" => CCompInstruction::MinusOne,
-            "D" => CCompInstruction::DRegister,
-            "A" => CCompInstruction::ARegister,
-            "M" => CCompInstruction::RAM,
-            "!D" => CCompInstruction::NotDRegister,
-            "!A" => CCompInstruction::NotARegister,
-            "!M" => CCompInstruction::NotRAM,
-            "-D" => CCompInstruction::MinusDRegister,
-            "-A" => CCompInstruction::MinusARegister,
-            "-M" => CCompInstruction::MinusRAM,
-            "D+1" => CCompInstruction::DRegisterPlusOne,
-            "A+1" => CCompInstruction::ARegisterPlusOne,
-            "M+1" => CCompInstruction::RAMPlusOne,
-            "D-1" => CCompInstruction::DRegisterMinusOne,
-            "A-1" => CCompInstruction::ARegisterMinusOne,
-            "M-1" => CCompInstruction::RAMMinusOne,
-            "D+A" => CCompInstruction::DRegisterPlusARegister,
-            "D+M" => CCompInstruction::DRegisterPlusRAM,
-            "D-A" => CCompInstruction::DRegisterMinusARegister,
-            "D-M" => CCompInstruction::DRegisterMinusRAM,
-            "A-D" => CCompInstruction::ARegisterMinusDRegister,
-            "M-D" => CCompInstruction::RAMMinusDRegister,
-            "D&A" => CCompInstruction::DRegisterAndARegister,
-            "D&M" => CCompInstruction::DRegisterAndRAM,
-            "D|A" => CCompInstruction::DRegisterOrARegister,
-            "D|M" => CCompInstruction::DRegisterOrRAM,
-            _ => panic!("Unknown computation instruction: {}", mnemonic),
-        };
+    if expr::parse(mnemonic).is_some() {
+        return Ok(Instruction::A(AInstruction::Expression(mnemonic)));
+    }
+
+    let kind = if mnemonic.contains(|c| "+-*()".contains(c)) {
+        ErrorKind::InvalidExpression(String::from(mnemonic))
+    } else if mnemonic.starts_with(|c: char| c.is_ascii_digit()) {
+        ErrorKind::InvalidAddress(String::from(mnemonic))
+    } else {
+        ErrorKind::InvalidSymbol(String::from(mnemonic))
+    };
+
+    Err(Error::new(
+        kind,
+        &Span::new(span.line, span.source, mnemonic),
+    ))
+}
+
+fn parse_constant<'a>(instruction: &'a str, span: &Span) -> Result<Instruction<'a>, Error> {
+    let (keyword, rest) = split_word(instruction);
+    let (name, value) = split_word(rest);
+
+    if !(keyword == ".equ" || keyword == ".define") || value.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidDirective(String::from(instruction)),
+            span,
+        ));
+    }
+
+    if !is_symbol(name) {
+        return Err(Error::new(
+            ErrorKind::InvalidSymbol(String::from(name)),
+            &Span::new(span.line, span.source, name),
+        ));
+    }
+
+    if expr::parse(value).is_none() {
+        return Err(Error::new(
+            ErrorKind::InvalidExpression(String::from(value)),
+            &Span::new(span.line, span.source, value),
+        ));
+    }
+
+    Ok(Instruction::Constant(ConstantInstruction { name, value }))
+}
+
+fn parse_global<'a>(instruction: &'a str, span: &Span) -> Result<Instruction<'a>, Error> {
+    let (keyword, name) = split_word(instruction);
+
+    if keyword != ".global" || name.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidDirective(String::from(instruction)),
+            span,
+        ));
+    }
+
+    if !is_symbol(name) {
+        return Err(Error::new(
+            ErrorKind::InvalidSymbol(String::from(name)),
+            &Span::new(span.line, span.source, name),
+        ));
+    }
+
+    Ok(Instruction::Global(GlobalInstruction { name }))
+}
+
+// Splits off the first whitespace-separated word, trimming the rest of the line
+fn split_word(text: &str) -> (&str, &str) {
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (&text[..end], text[end..].trim())
+}
+
+fn parse_label<'a>(instruction: &'a str, ip: usize, span: &Span) -> Result<Instruction<'a>, Error> {
+    if !instruction.ends_with(')') {
+        return Err(Error::new(
+            ErrorKind::InvalidLabel(String::from(instruction)),
+            span,
+        ));
+    }
+
+    let mnemonic = &instruction[1..instruction.len() - 1];
+    if !is_symbol(mnemonic) {
+        return Err(Error::new(
+            ErrorKind::InvalidSymbol(String::from(mnemonic)),
+            &Span::new(span.line, span.source, mnemonic),
+        ));
+    }
+
+    Ok(Instruction::Label(LabelInstruction {
+        name: mnemonic,
+        ptr: ip as u16,
+    }))
+}
+
+fn parse_c_instruction(
+    instruction: &str,
+    isa: Isa,
+    span: &Span,
+) -> Result<Instruction<'static>, Error> {
+    let (dest, rest) = match instruction.find('=') {
+        Some(index) => (Some(&instruction[..index]), &instruction[index + 1..]),
+        None => (None, instruction),
+    };
+
+    let (comp, jump) = match rest.find(';') {
+        Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+        None => (rest, None),
+    };
+
+    let dest = match dest {
+        Some(mnemonic) => {
+            CDestinationInstruction::from_mnemonic(mnemonic.trim()).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnknownDestination(String::from(mnemonic.trim())),
+                    &Span::new(span.line, span.source, mnemonic.trim()),
+                )
+            })?
+        }
+        None => CDestinationInstruction {
+            ram: false,
+            a_register: false,
+            d_register: false,
+        },
+    };
+
+    let jump = match jump {
+        Some(mnemonic) => CJumpInstruction::from_mnemonic(mnemonic.trim()).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownJump(String::from(mnemonic.trim())),
+                &Span::new(span.line, span.source, mnemonic.trim()),
+            )
+        })?,
+        None => CJumpInstruction {
+            greater_than: false,
+            equal: false,
+            lower_than: false,
+        },
+    };
+
+    let comp_mnemonic = comp.trim();
+    let comp = CCompInstruction::from_mnemonic(comp_mnemonic).ok_or_else(|| {
+        Error::new(
+            ErrorKind::UnknownComputation(String::from(comp_mnemonic)),
+            &Span::new(span.line, span.source, comp_mnemonic),
+        )
+    })?;
 
-        ip += 1;
-        instructions.push(Instruction::C(CInstruction { dest, jump, comp }));
+    if comp.is_shift() && isa != Isa::Extended {
+        return Err(Error::new(
+            ErrorKind::ExtendedInstruction(String::from(comp.mnemonic())),
+            &Span::new(span.line, span.source, comp_mnemonic),
+        ));
     }
 
-    instructions
+    Ok(Instruction::C(CInstruction { dest, jump, comp }))
 }
@@ -1,6 +1,10 @@
+use crate::error::*;
+use crate::expr;
 use crate::instructions::*;
 use std::collections::HashMap;
 
+const SCREEN: u16 = 16384;
+
 fn init_symbol_table<'a>(symbol_table: &'a mut HashMap<&str, u16>) -> &'a HashMap<&'a str, u16> {
     symbol_table.insert("R0", 0);
     symbol_table.insert("R1", 1);
@@ -18,7 +22,7 @@ fn init_symbol_table<'a>(symbol_table: &'a mut HashMap<&str, u16>) -> &'a HashMa
     symbol_table.insert("R13", 13);
     symbol_table.insert("R14", 14);
     symbol_table.insert("R15", 15);
-    symbol_table.insert("SCREEN", 16384);
+    symbol_table.insert("SCREEN", SCREEN);
     symbol_table.insert("KBD", 24576);
     symbol_table.insert("SP", 0);
     symbol_table.insert("LCL", 1);
@@ -29,29 +33,128 @@ fn init_symbol_table<'a>(symbol_table: &'a mut HashMap<&str, u16>) -> &'a HashMa
     symbol_table
 }
 
-pub fn resolve<'a>(instructions: &'a [Instruction]) -> HashMap<&'a str, u16> {
+pub fn predefined_address(name: &str) -> Option<u16> {
+    let mut symbol_table: HashMap<&str, u16> = HashMap::new();
+    init_symbol_table(&mut symbol_table).get(name).copied()
+}
+
+pub fn is_predefined(name: &str) -> bool {
+    predefined_address(name).is_some()
+}
+
+// Predefined symbols, labels placed at the `base` address and `.equ` constants.
+// Constants are evaluated in order, so they may only refer to the ones defined above
+pub fn definitions<'a>(
+    statements: &'a [Statement],
+    base: u16,
+) -> Result<HashMap<&'a str, u16>, Vec<Error>> {
     let mut symbols: HashMap<&str, u16> = HashMap::new();
-    let mut next_address: u16 = 15;
+    let mut errors: Vec<Error> = Vec::new();
 
     init_symbol_table(&mut symbols);
 
-    for instruction in instructions {
-        if let Instruction::Label(instruction) = instruction {
-            symbols.insert(instruction.name, instruction.ptr);
+    for statement in statements {
+        if let Instruction::Label(instruction) = &statement.instruction {
+            symbols.insert(instruction.name, instruction.ptr.wrapping_add(base));
         };
     }
 
-    for instruction in instructions {
-        if let Instruction::A(instruction) = instruction {
-            match instruction {
-                AInstruction::Literal(address) => address,
-                AInstruction::Mnemonic(name) => symbols.entry(name).or_insert_with(|| {
-                    next_address += 1;
-                    next_address
-                }),
-            };
+    for statement in statements {
+        if let Instruction::Constant(constant) = &statement.instruction {
+            if symbols.contains_key(constant.name) {
+                errors.push(Error::new(
+                    ErrorKind::SymbolRedefinition(String::from(constant.name)),
+                    &Span::new(statement.span.line, statement.span.source, constant.name),
+                ));
+                continue;
+            }
+
+            match expr::evaluate(constant.value, |name| symbols.get(name).copied()) {
+                Ok(value) => {
+                    symbols.insert(constant.name, value);
+                }
+                Err(kind) => errors.push(Error::new(
+                    kind,
+                    &Span::new(statement.span.line, statement.span.source, constant.value),
+                )),
+            }
         }
     }
 
-    symbols
+    if errors.is_empty() {
+        Ok(symbols)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn resolve<'a>(statements: &'a [Statement]) -> Result<HashMap<&'a str, u16>, Vec<Error>> {
+    let mut symbols = definitions(statements, 0)?;
+    let mut errors: Vec<Error> = Vec::new();
+    let mut next_address: u16 = 15;
+
+    for statement in statements {
+        let names = match &statement.instruction {
+            Instruction::A(AInstruction::Mnemonic(name)) => vec![*name],
+            Instruction::A(AInstruction::Expression(expression)) => expr::symbols(expression),
+            _ => continue,
+        };
+
+        for name in names {
+            if symbols.contains_key(name) {
+                continue;
+            }
+
+            if next_address + 1 >= SCREEN {
+                errors.push(Error::new(
+                    ErrorKind::RamOverflow(String::from(name)),
+                    &statement.span,
+                ));
+                continue;
+            }
+
+            next_address += 1;
+            symbols.insert(name, next_address);
+        }
+    }
+
+    // Expressions are stored under their own text, codegen looks them up like symbols.
+    // Negative `.equ` constants are fine elsewhere, but not as the value of an A-instruction.
+    // Symbols that did not fit into RAM are reported above already
+    for statement in statements {
+        match &statement.instruction {
+            Instruction::A(AInstruction::Expression(expression))
+                if expr::symbols(expression)
+                    .iter()
+                    .all(|name| symbols.contains_key(name)) =>
+            {
+                match expr::evaluate_address(expression, |name| symbols.get(name).copied()) {
+                    Ok(value) => {
+                        symbols.insert(expression, value);
+                    }
+                    Err(kind) => errors.push(Error::new(
+                        kind,
+                        &Span::new(statement.span.line, statement.span.source, expression),
+                    )),
+                }
+            }
+            Instruction::A(AInstruction::Mnemonic(name)) => match symbols.get(name) {
+                Some(value) if *value > 32767 => {
+                    let value = i64::from(*value as i16);
+                    errors.push(Error::new(
+                        ErrorKind::AddressOverflow(String::from(*name), value),
+                        &Span::new(statement.span.line, statement.span.source, name),
+                    ));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(symbols)
+    } else {
+        Err(errors)
+    }
 }
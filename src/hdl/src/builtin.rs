@@ -0,0 +1,421 @@
+// Chips the simulator implements natively, the same set `tools/builtInChips` provides.
+// They work on whole buses, so a 16-bit chip is a single component of the circuit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Nand,
+    Not,
+    And,
+    Or,
+    Xor,
+    Mux,
+    DMux,
+    Not16,
+    And16,
+    Or16,
+    Mux16,
+    Or8Way,
+    Mux4Way16,
+    Mux8Way16,
+    DMux4Way,
+    DMux8Way,
+    HalfAdder,
+    FullAdder,
+    Add16,
+    Inc16,
+    ALU,
+    DFF,
+    Bit,
+    Register,
+    ARegister,
+    DRegister,
+    PC,
+    RAM8,
+    RAM64,
+    RAM512,
+    RAM4K,
+    RAM16K,
+    ROM32K,
+    Screen,
+    Keyboard,
+    // Copies a bus, connects parts of different chips when their pins can not share a wire
+    Wire,
+}
+
+// Pins as `(name, width)`, the clocked inputs affect the outputs only after the clock ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spec {
+    pub inputs: &'static [(&'static str, u8)],
+    pub outputs: &'static [(&'static str, u8)],
+    pub clocked: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum State {
+    Register {
+        value: u16,
+        next: u16,
+    },
+    Memory {
+        cells: Vec<u16>,
+        write: Option<(u16, u16)>,
+    },
+}
+
+const GATE: Spec = Spec {
+    inputs: &[("a", 1), ("b", 1)],
+    outputs: &[("out", 1)],
+    clocked: &[],
+};
+
+const GATE16: Spec = Spec {
+    inputs: &[("a", 16), ("b", 16)],
+    outputs: &[("out", 16)],
+    clocked: &[],
+};
+
+const REGISTER: Spec = Spec {
+    inputs: &[("in", 16), ("load", 1)],
+    outputs: &[("out", 16)],
+    clocked: &["in", "load"],
+};
+
+macro_rules! ram {
+    ($address:expr) => {
+        Spec {
+            inputs: &[("in", 16), ("load", 1), ("address", $address)],
+            outputs: &[("out", 16)],
+            clocked: &["in", "load"],
+        }
+    };
+}
+
+const NAMES: [(&str, Builtin); 35] = [
+    ("Nand", Builtin::Nand),
+    ("Not", Builtin::Not),
+    ("And", Builtin::And),
+    ("Or", Builtin::Or),
+    ("Xor", Builtin::Xor),
+    ("Mux", Builtin::Mux),
+    ("DMux", Builtin::DMux),
+    ("Not16", Builtin::Not16),
+    ("And16", Builtin::And16),
+    ("Or16", Builtin::Or16),
+    ("Mux16", Builtin::Mux16),
+    ("Or8Way", Builtin::Or8Way),
+    ("Mux4Way16", Builtin::Mux4Way16),
+    ("Mux8Way16", Builtin::Mux8Way16),
+    ("DMux4Way", Builtin::DMux4Way),
+    ("DMux8Way", Builtin::DMux8Way),
+    ("HalfAdder", Builtin::HalfAdder),
+    ("FullAdder", Builtin::FullAdder),
+    ("Add16", Builtin::Add16),
+    ("Inc16", Builtin::Inc16),
+    ("ALU", Builtin::ALU),
+    ("DFF", Builtin::DFF),
+    ("Bit", Builtin::Bit),
+    ("Register", Builtin::Register),
+    ("ARegister", Builtin::ARegister),
+    ("DRegister", Builtin::DRegister),
+    ("PC", Builtin::PC),
+    ("RAM8", Builtin::RAM8),
+    ("RAM64", Builtin::RAM64),
+    ("RAM512", Builtin::RAM512),
+    ("RAM4K", Builtin::RAM4K),
+    ("RAM16K", Builtin::RAM16K),
+    ("ROM32K", Builtin::ROM32K),
+    ("Screen", Builtin::Screen),
+    ("Keyboard", Builtin::Keyboard),
+];
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        NAMES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, builtin)| *builtin)
+    }
+
+    pub fn name(self) -> &'static str {
+        match NAMES.iter().find(|(_, builtin)| *builtin == self) {
+            Some((name, _)) => name,
+            None => "Wire",
+        }
+    }
+
+    pub fn spec(self) -> Spec {
+        use Builtin::*;
+
+        match self {
+            Nand | And | Or | Xor => GATE,
+            Not => Spec {
+                inputs: &[("in", 1)],
+                outputs: &[("out", 1)],
+                clocked: &[],
+            },
+            Mux => Spec {
+                inputs: &[("a", 1), ("b", 1), ("sel", 1)],
+                outputs: &[("out", 1)],
+                clocked: &[],
+            },
+            DMux => Spec {
+                inputs: &[("in", 1), ("sel", 1)],
+                outputs: &[("a", 1), ("b", 1)],
+                clocked: &[],
+            },
+            Not16 | Inc16 | Wire => Spec {
+                inputs: &[("in", 16)],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+            And16 | Or16 | Add16 => GATE16,
+            Mux16 => Spec {
+                inputs: &[("a", 16), ("b", 16), ("sel", 1)],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+            Or8Way => Spec {
+                inputs: &[("in", 8)],
+                outputs: &[("out", 1)],
+                clocked: &[],
+            },
+            Mux4Way16 => Spec {
+                inputs: &[("a", 16), ("b", 16), ("c", 16), ("d", 16), ("sel", 2)],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+            Mux8Way16 => Spec {
+                inputs: &[
+                    ("a", 16),
+                    ("b", 16),
+                    ("c", 16),
+                    ("d", 16),
+                    ("e", 16),
+                    ("f", 16),
+                    ("g", 16),
+                    ("h", 16),
+                    ("sel", 3),
+                ],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+            DMux4Way => Spec {
+                inputs: &[("in", 1), ("sel", 2)],
+                outputs: &[("a", 1), ("b", 1), ("c", 1), ("d", 1)],
+                clocked: &[],
+            },
+            DMux8Way => Spec {
+                inputs: &[("in", 1), ("sel", 3)],
+                outputs: &[
+                    ("a", 1),
+                    ("b", 1),
+                    ("c", 1),
+                    ("d", 1),
+                    ("e", 1),
+                    ("f", 1),
+                    ("g", 1),
+                    ("h", 1),
+                ],
+                clocked: &[],
+            },
+            HalfAdder => Spec {
+                inputs: &[("a", 1), ("b", 1)],
+                outputs: &[("sum", 1), ("carry", 1)],
+                clocked: &[],
+            },
+            FullAdder => Spec {
+                inputs: &[("a", 1), ("b", 1), ("c", 1)],
+                outputs: &[("sum", 1), ("carry", 1)],
+                clocked: &[],
+            },
+            ALU => Spec {
+                inputs: &[
+                    ("x", 16),
+                    ("y", 16),
+                    ("zx", 1),
+                    ("nx", 1),
+                    ("zy", 1),
+                    ("ny", 1),
+                    ("f", 1),
+                    ("no", 1),
+                ],
+                outputs: &[("out", 16), ("zr", 1), ("ng", 1)],
+                clocked: &[],
+            },
+            DFF => Spec {
+                inputs: &[("in", 1)],
+                outputs: &[("out", 1)],
+                clocked: &["in"],
+            },
+            Bit => Spec {
+                inputs: &[("in", 1), ("load", 1)],
+                outputs: &[("out", 1)],
+                clocked: &["in", "load"],
+            },
+            Register | ARegister | DRegister => REGISTER,
+            PC => Spec {
+                inputs: &[("in", 16), ("load", 1), ("inc", 1), ("reset", 1)],
+                outputs: &[("out", 16)],
+                clocked: &["in", "load", "inc", "reset"],
+            },
+            RAM8 => ram!(3),
+            RAM64 => ram!(6),
+            RAM512 => ram!(9),
+            RAM4K => ram!(12),
+            RAM16K => ram!(14),
+            Screen => ram!(13),
+            ROM32K => Spec {
+                inputs: &[("address", 15)],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+            Keyboard => Spec {
+                inputs: &[],
+                outputs: &[("out", 16)],
+                clocked: &[],
+            },
+        }
+    }
+
+    // Registers and memories keep their value between the clock cycles
+    pub fn state(self) -> Option<State> {
+        use Builtin::*;
+
+        match self {
+            DFF | Bit | Register | ARegister | DRegister | PC | Keyboard => {
+                Some(State::Register { value: 0, next: 0 })
+            }
+            RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen | ROM32K => Some(State::Memory {
+                cells: vec![0; 1 << self.spec().inputs.last().unwrap().1],
+                write: None,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_clocked(self) -> bool {
+        !self.spec().clocked.is_empty()
+    }
+
+    pub fn eval(self, inputs: &[u16], state: Option<&State>, outputs: &mut [u16]) {
+        use Builtin::*;
+
+        let bit = |value: bool| value as u16;
+        let i = inputs;
+
+        match self {
+            Nand => outputs[0] = bit(i[0] & i[1] == 0),
+            Not => outputs[0] = bit(i[0] == 0),
+            And => outputs[0] = i[0] & i[1],
+            Or => outputs[0] = i[0] | i[1],
+            Xor => outputs[0] = i[0] ^ i[1],
+            Mux | Mux16 => outputs[0] = if i[2] == 0 { i[0] } else { i[1] },
+            DMux | DMux4Way | DMux8Way => {
+                for (index, output) in outputs.iter_mut().enumerate() {
+                    *output = bit(i[1] as usize == index) & i[0];
+                }
+            }
+            Not16 => outputs[0] = !i[0],
+            And16 => outputs[0] = i[0] & i[1],
+            Or16 => outputs[0] = i[0] | i[1],
+            Or8Way => outputs[0] = bit(i[0] != 0),
+            Mux4Way16 => outputs[0] = i[i[4] as usize],
+            Mux8Way16 => outputs[0] = i[i[8] as usize],
+            HalfAdder => {
+                outputs[0] = i[0] ^ i[1];
+                outputs[1] = i[0] & i[1];
+            }
+            FullAdder => {
+                let sum = i[0] + i[1] + i[2];
+                outputs[0] = sum & 1;
+                outputs[1] = sum >> 1;
+            }
+            Add16 => outputs[0] = i[0].wrapping_add(i[1]),
+            Inc16 => outputs[0] = i[0].wrapping_add(1),
+            ALU => {
+                let mut x = if i[2] == 1 { 0 } else { i[0] };
+                if i[3] == 1 {
+                    x = !x;
+                }
+
+                let mut y = if i[4] == 1 { 0 } else { i[1] };
+                if i[5] == 1 {
+                    y = !y;
+                }
+
+                let mut out = if i[6] == 1 { x.wrapping_add(y) } else { x & y };
+                if i[7] == 1 {
+                    out = !out;
+                }
+
+                outputs[0] = out;
+                outputs[1] = bit(out == 0);
+                outputs[2] = out >> 15;
+            }
+            Wire => outputs[0] = i[0],
+            DFF | Bit | Register | ARegister | DRegister | PC | Keyboard => {
+                if let Some(State::Register { value, .. }) = state {
+                    outputs[0] = *value;
+                }
+            }
+            RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen => {
+                if let Some(State::Memory { cells, .. }) = state {
+                    outputs[0] = cells[i[2] as usize];
+                }
+            }
+            ROM32K => {
+                if let Some(State::Memory { cells, .. }) = state {
+                    outputs[0] = cells[i[0] as usize];
+                }
+            }
+        }
+    }
+
+    // The clock goes up: the chip reads its inputs, which take effect when the clock goes down
+    pub fn latch(self, inputs: &[u16], state: &mut State) {
+        use Builtin::*;
+
+        match (self, state) {
+            (DFF, State::Register { next, .. }) => *next = inputs[0],
+            (Bit | Register | ARegister | DRegister, State::Register { value, next }) => {
+                *next = if inputs[1] == 1 { inputs[0] } else { *value };
+            }
+            (PC, State::Register { value, next }) => {
+                *next = if inputs[3] == 1 {
+                    0
+                } else if inputs[1] == 1 {
+                    inputs[0]
+                } else if inputs[2] == 1 {
+                    value.wrapping_add(1)
+                } else {
+                    *value
+                };
+            }
+            (RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen, State::Memory { write, .. }) => {
+                *write = if inputs[1] == 1 {
+                    Some((inputs[2], inputs[0]))
+                } else {
+                    None
+                };
+            }
+            _ => (),
+        }
+    }
+
+    // The clock goes down, returns whether the chip changed its state
+    pub fn commit(self, state: &mut State) -> bool {
+        match state {
+            State::Register { value, next } if value != next => {
+                *value = *next;
+                true
+            }
+            State::Memory { cells, write } => match write.take() {
+                Some((address, word)) if cells[address as usize] != word => {
+                    cells[address as usize] = word;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+// `name` or `name[16]` in the `IN` and `OUT` sections
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pin {
+    pub name: String,
+    pub width: u8,
+}
+
+// `name`, `name[3]` or `name[0..7]`, the range is inclusive
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bus {
+    pub name: String,
+    pub range: Option<(u8, u8)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bus(Bus),
+    True,
+    False,
+}
+
+// `pin=value` inside of a part, the pin belongs to the part and the value to the chip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub pin: Bus,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub name: String,
+    pub connections: Vec<Connection>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chip {
+    pub name: String,
+    pub inputs: Vec<Pin>,
+    pub outputs: Vec<Pin>,
+    pub parts: Vec<Part>,
+    // `BUILTIN Name;` chips are implemented by the simulator
+    pub builtin: Option<String>,
+    pub clocked: Vec<String>,
+}
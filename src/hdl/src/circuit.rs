@@ -0,0 +1,809 @@
+use crate::builtin::{Builtin, State};
+use crate::chip::*;
+use crate::error::Error;
+use crate::parser;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Nodes holding the `false` and `true` constants
+const FALSE: u32 = 0;
+const TRUE: u32 = 1;
+const NO_STATE: u32 = u32::MAX;
+
+// Bits `pin_lo..pin_lo + width` of a component pin connected to bits `node_lo..` of a node
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Link {
+    pin: u8,
+    pin_lo: u8,
+    width: u8,
+    node_lo: u8,
+    node: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Component {
+    builtin: Builtin,
+    state: u32,
+    inputs: (u32, u32),
+    outputs: (u32, u32),
+    rank: u32,
+}
+
+// Names and widths of the pins of a chip
+type Pins = Vec<(String, u8)>;
+
+// A chip is either described in HDL or built into the simulator
+#[derive(Debug, Clone)]
+enum Definition {
+    Hdl(Rc<Chip>, String),
+    Builtin(Builtin),
+}
+
+impl Definition {
+    fn pins(&self) -> (Pins, Pins) {
+        let pins = |pins: &[Pin]| {
+            pins.iter()
+                .map(|pin| (pin.name.clone(), pin.width))
+                .collect::<Vec<_>>()
+        };
+        let spec_pins = |pins: &[(&str, u8)]| {
+            pins.iter()
+                .map(|(name, width)| (String::from(*name), *width))
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            Definition::Hdl(chip, _) => (pins(&chip.inputs), pins(&chip.outputs)),
+            Definition::Builtin(builtin) => {
+                let spec = builtin.spec();
+                (spec_pins(spec.inputs), spec_pins(spec.outputs))
+            }
+        }
+    }
+}
+
+// Chips are looked up in the directory of the loaded chip first, then among the builtin ones
+struct Library {
+    directory: PathBuf,
+    chips: HashMap<String, Definition>,
+}
+
+impl Library {
+    fn definition(&mut self, name: &str) -> Result<Option<Definition>, Error> {
+        if let Some(definition) = self.chips.get(name) {
+            return Ok(Some(definition.clone()));
+        }
+
+        let path = self.directory.join(format!("{}.hdl", name));
+        let definition = if path.is_file() {
+            let file = path.display().to_string();
+            let source = fs::read_to_string(&path)
+                .map_err(|_| Error::new(&file, 0, &format!("could not read `{}`", file)))?;
+            let chip = parser::parse(&file, &source)?;
+
+            if chip.name != name {
+                return Err(Error::new(
+                    &file,
+                    1,
+                    &format!(
+                        "`{}` describes chip `{}` instead of `{}`",
+                        file, chip.name, name
+                    ),
+                ));
+            }
+
+            match &chip.builtin {
+                Some(builtin) => match Builtin::from_name(builtin) {
+                    Some(builtin) => Definition::Builtin(builtin),
+                    None => {
+                        return Err(Error::new(
+                            &file,
+                            1,
+                            &format!("there is no builtin chip `{}`", builtin),
+                        ))
+                    }
+                },
+                None => Definition::Hdl(Rc::new(chip), file),
+            }
+        } else {
+            match Builtin::from_name(name) {
+                Some(builtin) => Definition::Builtin(builtin),
+                None => return Ok(None),
+            }
+        };
+
+        self.chips.insert(String::from(name), definition.clone());
+        Ok(Some(definition))
+    }
+}
+
+// A chip flattened into builtin components connected by nodes, the wires of the circuit.
+// Changes of nodes propagate through the components reading them in topological order,
+// clocked components latch their inputs on `tick` and update their outputs on `tock`
+pub struct Circuit {
+    name: String,
+    values: Vec<u16>,
+    widths: Vec<u8>,
+    components: Vec<Component>,
+    links: Vec<Link>,
+    states: Vec<State>,
+    // Components reading each node, the lowest bit tells whether they read it combinationally
+    readers: Vec<u32>,
+    reader_offsets: Vec<u32>,
+    // Pins and internal wires of the chip itself
+    pins: HashMap<String, u32>,
+    // Builtin components with a state, like `ARegister` or `RAM16K`, by name
+    parts: Vec<(&'static str, u32)>,
+    queue: Vec<Vec<u32>>,
+    queued: Vec<bool>,
+    pending: usize,
+    touched: Vec<u32>,
+    is_touched: Vec<bool>,
+    latched: Vec<u32>,
+}
+
+struct Builder {
+    library: Library,
+    circuit: Circuit,
+    edges: Vec<(u32, u32)>,
+    stack: Vec<String>,
+}
+
+impl Circuit {
+    // Loads the chip and the chips it is made of from the directory of the file
+    pub fn load(path: &Path) -> Result<Circuit, Error> {
+        let directory = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Circuit::new(&directory, &name)
+    }
+
+    pub fn new(directory: &Path, name: &str) -> Result<Circuit, Error> {
+        let mut builder = Builder {
+            library: Library {
+                directory: directory.to_path_buf(),
+                chips: HashMap::new(),
+            },
+            circuit: Circuit {
+                name: String::from(name),
+                values: vec![0, 0xFFFF],
+                widths: vec![16, 16],
+                components: Vec::new(),
+                links: Vec::new(),
+                states: Vec::new(),
+                readers: Vec::new(),
+                reader_offsets: Vec::new(),
+                pins: HashMap::new(),
+                parts: Vec::new(),
+                queue: Vec::new(),
+                queued: Vec::new(),
+                pending: 0,
+                touched: Vec::new(),
+                is_touched: Vec::new(),
+                latched: Vec::new(),
+            },
+            edges: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        let file = directory
+            .join(format!("{}.hdl", name))
+            .display()
+            .to_string();
+        let definition = builder
+            .library
+            .definition(name)?
+            .ok_or_else(|| Error::new(&file, 0, &format!("chip `{}` is not found", name)))?;
+
+        let (inputs, outputs) = definition.pins();
+        let mut pins: HashMap<String, u32> = HashMap::new();
+        for (name, width) in inputs.iter().chain(&outputs) {
+            pins.insert(name.clone(), builder.node(*width));
+        }
+
+        let wires = match &definition {
+            Definition::Hdl(chip, file) => builder.instantiate(chip, file, &pins)?,
+            Definition::Builtin(builtin) => {
+                let link = |pin: usize, (name, width): &(String, u8)| Link {
+                    pin: pin as u8,
+                    pin_lo: 0,
+                    width: *width,
+                    node_lo: 0,
+                    node: pins[name],
+                };
+
+                let sources = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(pin, input)| link(pin, input));
+                let targets = outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(pin, output)| link(pin, output));
+                builder.component(*builtin, sources.collect(), targets.collect());
+                HashMap::new()
+            }
+        };
+
+        builder.circuit.pins = wires.into_iter().chain(pins).collect();
+        builder.finish(&file)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Value of a pin or an internal wire of the chip, along with its width
+    pub fn pin(&self, name: &str) -> Option<(u16, u8)> {
+        self.pins
+            .get(name)
+            .map(|node| (self.values[*node as usize], self.widths[*node as usize]))
+    }
+
+    pub fn set_pin(&mut self, name: &str, value: u16) -> bool {
+        match self.pins.get(name) {
+            Some(node) => {
+                let node = *node;
+                let value = value & mask(self.widths[node as usize]);
+                self.write(node, 0, 16, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn part(&self, name: &str) -> Option<u32> {
+        self.parts
+            .iter()
+            .find(|(part, _)| *part == name)
+            .map(|(_, component)| *component)
+    }
+
+    // Value of a register, e.g. `DRegister[]`, or of a memory cell, e.g. `RAM16K[0]`,
+    // of the first builtin part with that name. Registers ignore the index, and like in the
+    // hardware simulator the parts show what they latched on the tick before the tock
+    pub fn part_value(&self, name: &str, index: Option<u16>) -> Option<u16> {
+        let component = self.components[self.part(name)? as usize];
+        match (&self.states[component.state as usize], index) {
+            (State::Register { next, .. }, _) => Some(*next),
+            (
+                State::Memory {
+                    write: Some((address, word)),
+                    ..
+                },
+                Some(index),
+            ) if *address == index => Some(*word),
+            (State::Memory { cells, .. }, Some(index)) => cells.get(index as usize).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn set_part_value(&mut self, name: &str, index: Option<u16>, word: u16) -> bool {
+        let component = match self.part(name) {
+            Some(component) => component,
+            None => return false,
+        };
+
+        let state = self.components[component as usize].state;
+        let is_set = match (&mut self.states[state as usize], index) {
+            (State::Register { value, next }, _) => {
+                *value = word;
+                *next = word;
+                true
+            }
+            (State::Memory { cells, .. }, Some(index)) if (index as usize) < cells.len() => {
+                cells[index as usize] = word;
+                true
+            }
+            _ => false,
+        };
+
+        if is_set {
+            self.schedule(component);
+            self.touch(component);
+        }
+
+        is_set
+    }
+
+    // Loads the contents of a memory part, e.g. a program into `ROM32K`
+    pub fn load_part(&mut self, name: &str, words: &[u16]) -> bool {
+        let component = match self.part(name) {
+            Some(component) => component,
+            None => return false,
+        };
+
+        let state = self.components[component as usize].state;
+        match &mut self.states[state as usize] {
+            State::Memory { cells, .. } => {
+                for (index, cell) in cells.iter_mut().enumerate() {
+                    *cell = words.get(index).copied().unwrap_or(0);
+                }
+            }
+            State::Register { .. } => return false,
+        }
+
+        self.schedule(component);
+        true
+    }
+
+    // Settles the combinational logic after the inputs changed
+    pub fn eval(&mut self) {
+        let mut rank = 0;
+
+        while self.pending > 0 {
+            while let Some(component) = self.queue[rank].pop() {
+                self.queued[component as usize] = false;
+                self.pending -= 1;
+                self.evaluate(component);
+            }
+
+            rank += 1;
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.eval();
+
+        for component in std::mem::take(&mut self.touched) {
+            self.is_touched[component as usize] = false;
+            let inputs = self.inputs(component);
+            let Component { builtin, state, .. } = self.components[component as usize];
+            builtin.latch(&inputs, &mut self.states[state as usize]);
+            self.latched.push(component);
+        }
+    }
+
+    pub fn tock(&mut self) {
+        for component in std::mem::take(&mut self.latched) {
+            let Component { builtin, state, .. } = self.components[component as usize];
+            if builtin.commit(&mut self.states[state as usize]) {
+                self.schedule(component);
+                self.touch(component);
+            }
+        }
+
+        self.eval();
+    }
+
+    fn inputs(&self, component: u32) -> [u16; 10] {
+        let (start, end) = self.components[component as usize].inputs;
+        let mut inputs = [0; 10];
+
+        for link in &self.links[start as usize..end as usize] {
+            let bits = (self.values[link.node as usize] >> link.node_lo) & mask(link.width);
+            inputs[link.pin as usize] |= bits << link.pin_lo;
+        }
+
+        inputs
+    }
+
+    fn evaluate(&mut self, component: u32) {
+        let inputs = self.inputs(component);
+        let Component {
+            builtin,
+            state,
+            outputs: (start, end),
+            ..
+        } = self.components[component as usize];
+
+        let mut outputs = [0; 10];
+        builtin.eval(&inputs, self.states.get(state as usize), &mut outputs);
+
+        for index in start..end {
+            let link = self.links[index as usize];
+            let bits = (outputs[link.pin as usize] >> link.pin_lo) & mask(link.width);
+            self.write(link.node, link.node_lo, link.width, bits);
+        }
+    }
+
+    fn write(&mut self, node: u32, lo: u8, width: u8, bits: u16) {
+        let old = self.values[node as usize];
+        let new = (old & !(mask(width) << lo)) | (bits << lo);
+        if new == old {
+            return;
+        }
+
+        self.values[node as usize] = new;
+        let (start, end) = (
+            self.reader_offsets[node as usize],
+            self.reader_offsets[node as usize + 1],
+        );
+
+        for index in start..end {
+            let reader = self.readers[index as usize];
+            let component = reader >> 1;
+            if reader & 1 == 1 {
+                self.schedule(component);
+            }
+
+            if self.components[component as usize].builtin.is_clocked() {
+                self.touch(component);
+            }
+        }
+    }
+
+    fn schedule(&mut self, component: u32) {
+        if !self.queued[component as usize] {
+            self.queued[component as usize] = true;
+            self.pending += 1;
+            let rank = self.components[component as usize].rank;
+            self.queue[rank as usize].push(component);
+        }
+    }
+
+    // Clocked components latch on the next tick only when their inputs or state changed
+    fn touch(&mut self, component: u32) {
+        if !self.is_touched[component as usize] {
+            self.is_touched[component as usize] = true;
+            self.touched.push(component);
+        }
+    }
+}
+
+fn mask(width: u8) -> u16 {
+    if width >= 16 {
+        0xFFFF
+    } else {
+        (1 << width) - 1
+    }
+}
+
+// Bits of `pin[from..to]` within a pin of the given width
+fn range(bus: &Bus, width: u8) -> Option<(u8, u8)> {
+    match bus.range {
+        None => Some((0, width)),
+        Some((from, to)) if to < width => Some((from, to - from + 1)),
+        Some(_) => None,
+    }
+}
+
+impl Builder {
+    fn node(&mut self, width: u8) -> u32 {
+        self.circuit.values.push(0);
+        self.circuit.widths.push(width);
+        self.circuit.values.len() as u32 - 1
+    }
+
+    fn component(&mut self, builtin: Builtin, inputs: Vec<Link>, outputs: Vec<Link>) {
+        let circuit = &mut self.circuit;
+        let index = circuit.components.len() as u32;
+        let spec = builtin.spec();
+
+        let state = match builtin.state() {
+            Some(state) => {
+                circuit.states.push(state);
+                circuit.parts.push((builtin.name(), index));
+                circuit.states.len() as u32 - 1
+            }
+            None => NO_STATE,
+        };
+
+        let start = circuit.links.len() as u32;
+        for link in &inputs {
+            if link.node != FALSE && link.node != TRUE {
+                let is_combinational = !spec.clocked.contains(&spec.inputs[link.pin as usize].0);
+                self.edges
+                    .push((link.node, index << 1 | is_combinational as u32));
+            }
+        }
+        circuit.links.extend(inputs);
+
+        let middle = circuit.links.len() as u32;
+        circuit.links.extend(outputs);
+
+        circuit.components.push(Component {
+            builtin,
+            state,
+            inputs: (start, middle),
+            outputs: (middle, circuit.links.len() as u32),
+            rank: 0,
+        });
+    }
+
+    // Adds the parts of the chip to the circuit, the pins of the chip are already nodes.
+    // Returns the internal wires of the chip
+    fn instantiate(
+        &mut self,
+        chip: &Chip,
+        file: &str,
+        pins: &HashMap<String, u32>,
+    ) -> Result<HashMap<String, u32>, Error> {
+        let inputs: HashSet<&str> = chip.inputs.iter().map(|pin| pin.name.as_str()).collect();
+        let mut wires: HashMap<String, u32> = HashMap::new();
+        let mut driven: HashSet<String> = HashSet::new();
+        let mut used: Vec<(String, usize)> = Vec::new();
+
+        for part in &chip.parts {
+            let error = |message: String| Error::new(file, part.line, &message);
+            let definition = self
+                .library
+                .definition(&part.name)?
+                .ok_or_else(|| error(format!("chip `{}` is not found", part.name)))?;
+
+            if self.stack.contains(&part.name) || part.name == chip.name {
+                return Err(error(format!("chip `{}` contains itself", part.name)));
+            }
+
+            let (part_inputs, part_outputs) = definition.pins();
+            let mut sources: Vec<Vec<Link>> = vec![Vec::new(); part_inputs.len()];
+            let mut targets: Vec<Vec<Link>> = vec![Vec::new(); part_outputs.len()];
+
+            for connection in &part.connections {
+                let find = |pins: &Pins| {
+                    pins.iter()
+                        .position(|(name, _)| *name == connection.pin.name)
+                };
+
+                let (is_input, pin) = match (find(&part_inputs), find(&part_outputs)) {
+                    (Some(pin), _) => (true, pin),
+                    (None, Some(pin)) => (false, pin),
+                    (None, None) => {
+                        return Err(error(format!(
+                            "`{}` has no pin `{}`",
+                            part.name, connection.pin.name
+                        )))
+                    }
+                };
+
+                let pin_width = if is_input {
+                    part_inputs[pin].1
+                } else {
+                    part_outputs[pin].1
+                };
+
+                let (pin_lo, width) = range(&connection.pin, pin_width).ok_or_else(|| {
+                    error(format!(
+                        "`{}` of `{}` is only {} bits wide",
+                        connection.pin.name, part.name, pin_width
+                    ))
+                })?;
+
+                let (node, node_lo) = match &connection.value {
+                    Value::True | Value::False if !is_input => {
+                        return Err(error(format!(
+                            "output `{}` of `{}` can not be connected to a constant",
+                            connection.pin.name, part.name
+                        )))
+                    }
+                    Value::True => (TRUE, 0),
+                    Value::False => (FALSE, 0),
+                    Value::Bus(bus) => match pins.get(&bus.name) {
+                        Some(node) => {
+                            let is_chip_input = inputs.contains(bus.name.as_str());
+                            if is_input && !is_chip_input {
+                                return Err(error(format!(
+                                    "output `{}` of `{}` can not feed a part",
+                                    bus.name, chip.name
+                                )));
+                            }
+
+                            if !is_input && is_chip_input {
+                                return Err(error(format!(
+                                    "input `{}` of `{}` can not be driven by a part",
+                                    bus.name, chip.name
+                                )));
+                            }
+
+                            let bus_width = self.circuit.widths[*node as usize];
+                            let (node_lo, node_width) = range(bus, bus_width).ok_or_else(|| {
+                                error(format!("`{}` is only {} bits wide", bus.name, bus_width))
+                            })?;
+
+                            if node_width != width {
+                                return Err(error(format!(
+                                    "`{}` is {} bits wide but `{}` of `{}` is {}",
+                                    bus.name, node_width, connection.pin.name, part.name, width
+                                )));
+                            }
+
+                            (*node, node_lo)
+                        }
+                        None => {
+                            if bus.range.is_some() {
+                                return Err(error(format!(
+                                    "internal wire `{}` can not be indexed",
+                                    bus.name
+                                )));
+                            }
+
+                            let node = match wires.get(&bus.name) {
+                                Some(node) => *node,
+                                None => {
+                                    let node = self.node(width);
+                                    wires.insert(bus.name.clone(), node);
+                                    node
+                                }
+                            };
+
+                            let wire_width = self.circuit.widths[node as usize];
+                            if wire_width != width {
+                                return Err(error(format!(
+                                    "`{}` is {} bits wide but `{}` of `{}` is {}",
+                                    bus.name, wire_width, connection.pin.name, part.name, width
+                                )));
+                            }
+
+                            if is_input {
+                                used.push((bus.name.clone(), part.line));
+                            } else if !driven.insert(bus.name.clone()) {
+                                return Err(error(format!(
+                                    "internal wire `{}` is driven twice",
+                                    bus.name
+                                )));
+                            }
+
+                            (node, 0)
+                        }
+                    },
+                };
+
+                let link = Link {
+                    pin: pin as u8,
+                    pin_lo,
+                    width,
+                    node_lo,
+                    node,
+                };
+
+                if is_input {
+                    sources[pin].push(link);
+                } else {
+                    targets[pin].push(link);
+                }
+            }
+
+            match definition {
+                Definition::Builtin(builtin) => {
+                    self.component(builtin, sources.concat(), targets.concat());
+                }
+                Definition::Hdl(subchip, subfile) => {
+                    let mut subpins: HashMap<String, u32> = HashMap::new();
+                    for (pin, (name, width)) in part_inputs.iter().enumerate() {
+                        let node = self.connect(&sources[pin], *width, true);
+                        subpins.insert(name.clone(), node);
+                    }
+
+                    for (pin, (name, width)) in part_outputs.iter().enumerate() {
+                        let node = self.connect(&targets[pin], *width, false);
+                        subpins.insert(name.clone(), node);
+                    }
+
+                    self.stack.push(chip.name.clone());
+                    self.instantiate(&subchip, &subfile, &subpins)?;
+                    self.stack.pop();
+                }
+            }
+        }
+
+        for (wire, line) in used {
+            if !driven.contains(&wire) {
+                return Err(Error::new(
+                    file,
+                    line,
+                    &format!("internal wire `{}` is not driven by any part", wire),
+                ));
+            }
+        }
+
+        Ok(wires)
+    }
+
+    // The node of a pin of a part described in HDL. A pin connected as a whole to a single
+    // node shares it, otherwise the pin gets its own node and a wire to the connections
+    fn connect(&mut self, links: &[Link], width: u8, is_input: bool) -> u32 {
+        match links {
+            [] if is_input => return FALSE,
+            [link]
+                if link.pin_lo == 0
+                    && link.node_lo == 0
+                    && link.width == width
+                    && self.circuit.widths[link.node as usize] == width =>
+            {
+                return link.node
+            }
+            _ => (),
+        }
+
+        let node = self.node(width);
+        let pin = Link {
+            pin: 0,
+            pin_lo: 0,
+            width,
+            node_lo: 0,
+            node,
+        };
+        let links: Vec<Link> = links.iter().map(|link| Link { pin: 0, ..*link }).collect();
+
+        if is_input {
+            self.component(Builtin::Wire, links, vec![pin]);
+        } else if !links.is_empty() {
+            self.component(Builtin::Wire, vec![pin], links);
+        }
+
+        node
+    }
+
+    // Orders the components by their combinational dependencies and settles the circuit
+    fn finish(mut self, file: &str) -> Result<Circuit, Error> {
+        let circuit = &mut self.circuit;
+        let nodes = circuit.values.len();
+
+        self.edges.sort_unstable();
+        circuit.reader_offsets = vec![0; nodes + 1];
+        for (node, _) in &self.edges {
+            circuit.reader_offsets[*node as usize + 1] += 1;
+        }
+        for node in 0..nodes {
+            circuit.reader_offsets[node + 1] += circuit.reader_offsets[node];
+        }
+        circuit.readers = self.edges.iter().map(|(_, reader)| *reader).collect();
+
+        // Longest path from the inputs and the clocked outputs, by Kahn's algorithm
+        let count = circuit.components.len();
+        let mut degrees = vec![0u32; count];
+        let successors = |circuit: &Circuit, component: usize| {
+            let (start, end) = circuit.components[component].outputs;
+            circuit.links[start as usize..end as usize]
+                .iter()
+                .flat_map(move |link| {
+                    let node = link.node as usize;
+                    circuit.readers[circuit.reader_offsets[node] as usize
+                        ..circuit.reader_offsets[node + 1] as usize]
+                        .iter()
+                        .filter(|reader| *reader & 1 == 1)
+                        .map(|reader| (*reader >> 1) as usize)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for component in 0..count {
+            for successor in successors(circuit, component) {
+                degrees[successor] += 1;
+            }
+        }
+
+        let mut ready: Vec<usize> = (0..count).filter(|c| degrees[*c] == 0).collect();
+        let mut ordered = 0;
+        while let Some(component) = ready.pop() {
+            ordered += 1;
+            let rank = circuit.components[component].rank;
+            for successor in successors(circuit, component) {
+                let other = &mut circuit.components[successor];
+                other.rank = other.rank.max(rank + 1);
+                degrees[successor] -= 1;
+                if degrees[successor] == 0 {
+                    ready.push(successor);
+                }
+            }
+        }
+
+        if ordered < count {
+            return Err(Error::new(
+                file,
+                0,
+                &format!("chip `{}` has a combinational loop", circuit.name),
+            ));
+        }
+
+        let ranks = circuit.components.iter().map(|c| c.rank).max().unwrap_or(0);
+        circuit.queue = vec![Vec::new(); ranks as usize + 1];
+        circuit.queued = vec![false; count];
+        circuit.is_touched = vec![false; count];
+
+        for component in 0..count as u32 {
+            circuit.schedule(component);
+            if circuit.components[component as usize].builtin.is_clocked() {
+                circuit.touch(component);
+            }
+        }
+
+        circuit.eval();
+        Ok(self.circuit)
+    }
+}
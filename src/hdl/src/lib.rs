@@ -0,0 +1,8 @@
+pub mod builtin;
+pub mod chip;
+pub mod circuit;
+pub mod error;
+pub mod parser;
+
+pub use crate::circuit::Circuit;
+pub use crate::error::Error;
@@ -0,0 +1,333 @@
+use crate::chip::*;
+use crate::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Symbol(&'static str),
+}
+
+pub fn parse(file: &str, source: &str) -> Result<Chip, Error> {
+    let tokens = tokenize(file, source)?;
+    let mut parser = Parser {
+        file,
+        tokens,
+        index: 0,
+    };
+
+    let chip = parser.chip()?;
+    match parser.tokens.get(parser.index) {
+        Some((line, _)) => Err(Error::new(file, *line, "unexpected text after the chip")),
+        None => Ok(chip),
+    }
+}
+
+fn tokenize(file: &str, source: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut line = 1;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '/' if next == Some('/') => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            '/' if next == Some('*') => {
+                let start = line;
+                index += 2;
+                while index < chars.len()
+                    && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+                {
+                    if chars[index] == '\n' {
+                        line += 1;
+                    }
+                    index += 1;
+                }
+
+                if index == chars.len() {
+                    return Err(Error::new(file, start, "unterminated comment"));
+                }
+                index += 1;
+            }
+            '.' if next == Some('.') => {
+                tokens.push((line, Token::Symbol("..")));
+                index += 1;
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let start = index;
+                while index < chars.len()
+                    && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+
+                tokens.push((line, Token::Word(chars[start..index].iter().collect())));
+                continue;
+            }
+            _ => {
+                let symbol = ["{", "}", "(", ")", "[", "]", ",", ";", "=", ":"]
+                    .iter()
+                    .find(|symbol| symbol.starts_with(c))
+                    .ok_or_else(|| {
+                        Error::new(file, line, &format!("unexpected character `{}`", c))
+                    })?;
+                tokens.push((line, Token::Symbol(symbol)));
+            }
+        }
+
+        index += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    file: &'a str,
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn chip(&mut self) -> Result<Chip, Error> {
+        self.keyword("CHIP")?;
+        let mut chip = Chip {
+            name: self.word()?,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            parts: Vec::new(),
+            builtin: None,
+            clocked: Vec::new(),
+        };
+        self.symbol("{")?;
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) if word == "IN" => {
+                    self.index += 1;
+                    chip.inputs.extend(self.pins()?);
+                }
+                Some(Token::Word(word)) if word == "OUT" => {
+                    self.index += 1;
+                    chip.outputs.extend(self.pins()?);
+                }
+                Some(Token::Word(word)) if word == "BUILTIN" => {
+                    self.index += 1;
+                    chip.builtin = Some(self.word()?);
+                    self.symbol(";")?;
+                }
+                Some(Token::Word(word)) if word == "CLOCKED" => {
+                    self.index += 1;
+                    chip.clocked = self.names()?;
+                }
+                Some(Token::Word(word)) if word == "PARTS" => {
+                    self.index += 1;
+                    self.symbol(":")?;
+                    while self.peek() != Some(Token::Symbol("}")) {
+                        chip.parts.push(self.part()?);
+                    }
+                }
+                Some(Token::Symbol("}")) => {
+                    self.index += 1;
+                    return Ok(chip);
+                }
+                _ => return Err(self.expected("`IN`, `OUT`, `PARTS:`, `BUILTIN` or `}`")),
+            }
+        }
+    }
+
+    fn pins(&mut self) -> Result<Vec<Pin>, Error> {
+        let mut pins: Vec<Pin> = Vec::new();
+
+        loop {
+            let name = self.word()?;
+            let width = if self.peek() == Some(Token::Symbol("[")) {
+                self.index += 1;
+                let width = self.number()?;
+                self.symbol("]")?;
+                width
+            } else {
+                1
+            };
+
+            if width == 0 || width > 16 {
+                return Err(self.error(&format!("pin `{}` must be 1 to 16 bits wide", name)));
+            }
+
+            pins.push(Pin { name, width });
+            if self.separator()? {
+                return Ok(pins);
+            }
+        }
+    }
+
+    fn names(&mut self) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = Vec::new();
+
+        loop {
+            names.push(self.word()?);
+            if self.separator()? {
+                return Ok(names);
+            }
+        }
+    }
+
+    // `,` continues the list and `;` ends it
+    fn separator(&mut self) -> Result<bool, Error> {
+        match self.peek() {
+            Some(Token::Symbol(",")) => {
+                self.index += 1;
+                Ok(false)
+            }
+            Some(Token::Symbol(";")) => {
+                self.index += 1;
+                Ok(true)
+            }
+            _ => Err(self.expected("`,` or `;`")),
+        }
+    }
+
+    fn part(&mut self) -> Result<Part, Error> {
+        let line = self.line();
+        let name = self.word()?;
+        let mut connections: Vec<Connection> = Vec::new();
+        self.symbol("(")?;
+
+        loop {
+            let pin = self.bus()?;
+            self.symbol("=")?;
+            let value = match self.peek() {
+                Some(Token::Word(word)) if word == "true" => {
+                    self.index += 1;
+                    Value::True
+                }
+                Some(Token::Word(word)) if word == "false" => {
+                    self.index += 1;
+                    Value::False
+                }
+                _ => Value::Bus(self.bus()?),
+            };
+
+            connections.push(Connection { pin, value });
+            match self.peek() {
+                Some(Token::Symbol(",")) => self.index += 1,
+                Some(Token::Symbol(")")) => {
+                    self.index += 1;
+                    break;
+                }
+                _ => return Err(self.expected("`,` or `)`")),
+            }
+        }
+
+        self.symbol(";")?;
+        Ok(Part {
+            name,
+            connections,
+            line,
+        })
+    }
+
+    fn bus(&mut self) -> Result<Bus, Error> {
+        let name = self.word()?;
+        if self.peek() != Some(Token::Symbol("[")) {
+            return Ok(Bus { name, range: None });
+        }
+
+        self.index += 1;
+        let from = self.number()?;
+        let to = if self.peek() == Some(Token::Symbol("..")) {
+            self.index += 1;
+            self.number()?
+        } else {
+            from
+        };
+        self.symbol("]")?;
+
+        if from > to || to > 15 {
+            return Err(self.error(&format!("invalid range `{}[{}..{}]`", name, from, to)));
+        }
+
+        Ok(Bus {
+            name,
+            range: Some((from, to)),
+        })
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == keyword => {
+                self.index += 1;
+                Ok(())
+            }
+            _ => Err(self.expected(&format!("`{}`", keyword))),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                self.index += 1;
+                Ok(word)
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    fn number(&mut self) -> Result<u8, Error> {
+        match self.peek() {
+            Some(Token::Word(word)) => match word.parse() {
+                Ok(number) => {
+                    self.index += 1;
+                    Ok(number)
+                }
+                Err(_) => Err(self.expected("a number")),
+            },
+            _ => Err(self.expected("a number")),
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        match self.peek() {
+            Some(Token::Symbol(found)) if found == symbol => {
+                self.index += 1;
+                Ok(())
+            }
+            _ => Err(self.expected(&format!("`{}`", symbol))),
+        }
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.index).map(|(_, token)| token.clone())
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.index).or_else(|| self.tokens.last()) {
+            Some((line, _)) => *line,
+            None => 1,
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::new(self.file, self.line(), message)
+    }
+
+    fn expected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                self.error(&format!("expected {}, found `{}`", expected, word))
+            }
+            Some(Token::Symbol(symbol)) => {
+                self.error(&format!("expected {}, found `{}`", expected, symbol))
+            }
+            None => self.error(&format!("expected {}, found the end of file", expected)),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use vm::config::OptimizerConfig;
+
+fn main() {
+    let config = OptimizerConfig::from_args();
+    let input_file = config.input_file.clone();
+
+    if let Err(errors) = vm::optimize_file(config) {
+        vm::abort("optimize", &input_file, &errors);
+    }
+}
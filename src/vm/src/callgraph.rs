@@ -0,0 +1,66 @@
+use crate::opcode::*;
+use std::collections::{HashMap, HashSet};
+
+pub fn is_defined(files: &[(&str, Vec<OpCode>)], function: &str) -> bool {
+    files.iter().any(|(_, opcodes)| {
+        opcodes
+            .iter()
+            .any(|opcode| matches!(opcode, OpCode::Function(defined) if defined.id == function))
+    })
+}
+
+// Functions `entry` calls, directly or through other functions, including itself.
+// Calls made by the code before the first function of a file are kept under "", which always runs
+pub fn reachable<'a>(files: &[(&str, Vec<OpCode<'a>>)], entry: &'a str) -> HashSet<&'a str> {
+    let mut calls: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (_, opcodes) in files {
+        let mut function = "";
+        for opcode in opcodes {
+            match opcode {
+                OpCode::Function(opcode) => {
+                    function = opcode.id;
+                    calls.entry(function).or_default();
+                }
+                OpCode::Call(opcode) => calls.entry(function).or_default().push(opcode.id),
+                _ => (),
+            }
+        }
+    }
+
+    let mut reachable: HashSet<&str> = HashSet::new();
+    let mut pending = vec![entry, ""];
+    while let Some(function) = pending.pop() {
+        if reachable.insert(function) {
+            if let Some(callees) = calls.get(function) {
+                pending.extend(callees);
+            }
+        }
+    }
+
+    reachable
+}
+
+// Drops the functions `entry` never gets to, returns their names in the order they were defined
+pub fn remove_unreachable<'a>(
+    files: &mut [(&str, Vec<OpCode<'a>>)],
+    entry: &'a str,
+) -> Vec<String> {
+    let reachable = reachable(files, entry);
+    let mut removed: Vec<String> = Vec::new();
+    for (_, opcodes) in files.iter_mut() {
+        // Code before the first function of a file is kept
+        let mut is_kept = true;
+        opcodes.retain(|opcode| {
+            if let OpCode::Function(function) = opcode {
+                is_kept = reachable.contains(function.id);
+                if !is_kept {
+                    removed.push(String::from(function.id));
+                }
+            }
+
+            is_kept
+        });
+    }
+
+    removed
+}
@@ -1,22 +1,49 @@
+use crate::config::Config;
 use crate::opcode::*;
+use std::collections::HashMap;
 use std::path::Path;
 
+// How far into a segment the stack caching code walks instead of adding the index, where both
+// take as many instructions
+const PUSH_WALK: u16 = 3;
+const POP_WALK: u16 = 8;
+
 pub struct Codegen {
     filename: String,
     assembly: String,
     label_counter: usize,
+    // Labels are local to the function they are defined in
+    function: String,
+    // Calls, returns and comparisons jump to routines shared by the whole program, and the
+    // stack is accessed with shorter sequences
+    optimize: bool,
+    // The top of the stack is kept in D instead of RAM while `cached` is set, and written back
+    // before anything that expects the whole stack in RAM
+    cache_stack: bool,
+    cached: bool,
+    // Addresses of the statics of the file, left to the assembler when empty
+    statics: HashMap<u16, u16>,
 }
 
 impl Codegen {
-    pub fn new(input_file: &str) -> Codegen {
+    pub fn new(input_file: &str, config: &Config) -> Codegen {
         Codegen {
             filename: String::from(Path::new(input_file).file_name().unwrap().to_str().unwrap()),
             assembly: String::new(),
             label_counter: 0,
+            function: String::new(),
+            optimize: config.optimize,
+            cache_stack: config.cache_stack,
+            cached: false,
+            statics: HashMap::new(),
         }
     }
 
     pub fn codegen(&mut self, opcodes: &[OpCode]) -> String {
+        if self.cache_stack {
+            return self.codegen_cached(opcodes);
+        }
+
         for opcode in opcodes {
             match opcode {
                 OpCode::Add => self.emit_2_args_computation("D+M", "add"),
@@ -31,35 +58,125 @@ impl Codegen {
                 OpCode::Return => self.emit_return(),
                 OpCode::Push(opcode) => self.emit_push(opcode),
                 OpCode::Pop(opcode) => self.emit_pop(opcode),
-                OpCode::Label(opcode) => self.emit_label(opcode.id),
-                OpCode::Goto(opcode) => self.emit_goto(opcode.id),
-                OpCode::IfGoto(opcode) => self.emit_if_goto(opcode.id),
+                OpCode::Label(opcode) => self.emit_label(&self.scoped(opcode.id)),
+                OpCode::Goto(opcode) => self.emit_goto(&self.scoped(opcode.id)),
+                OpCode::IfGoto(opcode) => self.emit_if_goto(&self.scoped(opcode.id)),
                 OpCode::Function(opcode) => self.emit_function(opcode),
                 OpCode::Call(opcode) => self.emit_call(opcode),
+                OpCode::Move(opcode) => self.emit_move(opcode),
             };
         }
 
         String::from(&self.assembly)
     }
 
-    pub fn emit_entry(&mut self) -> String {
-        self.emit_constant_to_d(256);
-        self.emit_d_to_address("SP");
-        self.emit_call(&CallOpCode {
-            id: "Sys.init",
-            args_count: 0,
-        });
+    pub fn emit_entry(&mut self, config: &Config) -> String {
+        let pointers = [
+            ("SP", Some(config.stack_pointer)),
+            ("LCL", config.local),
+            ("ARG", config.argument),
+            ("THIS", config.this),
+            ("THAT", config.that),
+        ];
+        for (pointer, value) in pointers.iter() {
+            if let Some(value) = value {
+                self.emit_constant_to_d(*value);
+                self.emit_d_to_address(pointer);
+            }
+        }
+
+        if let Some(entry) = &config.entry {
+            self.emit_call(&CallOpCode {
+                id: entry,
+                args_count: 0,
+            });
+        }
+
+        if self.optimize {
+            self.emit_shared_routines();
+        }
 
         String::from(&self.assembly)
     }
 
+    // The shared routines alone, for programs without the bootstrap code
+    pub fn emit_routines(&mut self) -> String {
+        self.emit_shared_routines();
+
+        String::from(&self.assembly)
+    }
+
+    // Gives the statics of the file addresses from `first` on, in the order they are used, and
+    // returns the address after the last one
+    pub fn place_statics(&mut self, first: u16, opcodes: &[OpCode]) -> u16 {
+        let accesses = opcodes.iter().flat_map(|opcode| match opcode {
+            OpCode::Push(push) => vec![(push.segment, push.i)],
+            OpCode::Pop(pop) => vec![(pop.segment, pop.i)],
+            OpCode::Move(opcode) => vec![
+                (opcode.from.segment, opcode.from.i),
+                (opcode.to.segment, opcode.to.i),
+            ],
+            _ => Vec::new(),
+        });
+
+        for (segment, i) in accesses {
+            if segment == Segment::Static {
+                let next = first + self.statics.len() as u16;
+                self.statics.entry(i).or_insert(next);
+            }
+        }
+
+        first + self.statics.len() as u16
+    }
+
+    // A-instruction pointing to a static, a symbol of the file unless the statics were placed
+    fn static_address(&self, i: u16) -> String {
+        match self.statics.get(&i) {
+            Some(address) => format!("@{}", address),
+            None => format!("@{}.{}", &self.filename, i),
+        }
+    }
+
     fn emit(&mut self, instruction: &str) {
         self.assembly.push_str(instruction);
         self.assembly.push('\n');
     }
 
+    // Return labels tell files apart by their name, which may have characters symbols can not
+    fn file_label(&self) -> String {
+        self.filename
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || ".$:".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    fn scoped(&self, id: &str) -> String {
+        if self.function.is_empty() {
+            String::from(id)
+        } else {
+            format!("{}${}", self.function, id)
+        }
+    }
+
+    fn emit_all(&mut self, instructions: &[&str]) {
+        for instruction in instructions {
+            self.emit(instruction);
+        }
+    }
+
+    fn return_label(&mut self) -> String {
+        self.label_counter += 1;
+        format!("__RET__{}__{}", self.file_label(), self.label_counter)
+    }
+
     fn emit_comment(&mut self, msg: &str) {
-        self.emit(&format!("\n// {}", msg));
+        self.emit(&format!("\n// vm: {}", msg));
     }
 
     fn emit_sp_dec(&mut self) {
@@ -112,6 +229,14 @@ impl Codegen {
 
     fn emit_if_goto(&mut self, id: &str) {
         self.emit_comment(&format!("if-goto {}", id));
+
+        if self.optimize {
+            self.emit_all(&["@SP", "AM=M-1", "D=M"]);
+            self.emit(&format!("@{}", id));
+            self.emit("D;JNE");
+            return;
+        }
+
         self.emit_sp_dec();
         self.emit_stack_to_d();
         self.emit(&format!("@{}", id));
@@ -135,6 +260,17 @@ impl Codegen {
     }
 
     fn emit_comparable_computation(&mut self, comparator: &str, comment: &str) {
+        if self.optimize {
+            self.emit_comment(comment);
+            let label = self.return_label();
+            self.emit(&format!("@{}", label));
+            self.emit("D=A");
+            self.emit(&format!("@__COMPARE_{}", comparator));
+            self.emit("0;JMP");
+            self.emit(&format!("({})", label));
+            return;
+        }
+
         self.emit_2_args_computation("M-D", comment);
         self.emit("D=M");
 
@@ -169,14 +305,18 @@ impl Codegen {
     fn emit_push(&mut self, opcode: &PushOpCode) {
         self.emit_comment(&format!("push {} {}", opcode.segment, opcode.i));
 
+        if self.optimize {
+            self.emit_compact_push(opcode);
+            return;
+        }
+
         match opcode.segment {
-            "local" | "argument" | "this" | "that" => {
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
                 let segment = match opcode.segment {
-                    "local" => "LCL",
-                    "argument" => "ARG",
-                    "this" => "THIS",
-                    "that" => "THAT",
-                    _ => panic!("Unsupported segment name: {}", opcode.segment),
+                    Segment::Local => "LCL",
+                    Segment::Argument => "ARG",
+                    Segment::This => "THIS",
+                    _ => "THAT",
                 };
 
                 // D = &(@segment + i)
@@ -188,30 +328,30 @@ impl Codegen {
                 self.emit_d_to_stack();
                 self.emit_sp_inc();
             }
-            "constant" => {
+            Segment::Constant => {
                 self.emit_constant_to_d(opcode.i);
                 self.emit_d_to_stack();
                 self.emit_sp_inc();
             }
-            "static" => {
+            Segment::Static => {
                 // D = @<filename>.<i>
-                self.emit(&format!("@{}.{}", &self.filename, opcode.i));
+                self.emit(&self.static_address(opcode.i));
                 self.emit("D=M");
                 self.emit_d_to_stack();
                 self.emit_sp_inc();
             }
-            "temp" => {
+            Segment::Temp => {
                 // D = &(i + 5)
                 self.emit(&format!("@{}", opcode.i + 5));
                 self.emit("D=M");
                 self.emit_d_to_stack();
                 self.emit_sp_inc();
             }
-            "pointer" => {
+            Segment::Pointer => {
                 let offset = match opcode.i {
                     0 => "THIS",
                     1 => "THAT",
-                    _ => panic!("Unknown pointer offset: {}", opcode.i),
+                    _ => unreachable!("the parser only accepts pointer 0 and 1"),
                 };
 
                 self.emit(&format!("@{}", offset));
@@ -219,21 +359,24 @@ impl Codegen {
                 self.emit_d_to_stack();
                 self.emit_sp_inc();
             }
-            _ => panic!("Unknown segment name: {}", opcode.segment),
         }
     }
 
     fn emit_pop(&mut self, opcode: &PopOpCode) {
         self.emit_comment(&format!("pop {} {}", opcode.segment, opcode.i));
 
+        if self.optimize {
+            self.emit_compact_pop(opcode);
+            return;
+        }
+
         match opcode.segment {
-            "local" | "argument" | "this" | "that" => {
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
                 let segment = match opcode.segment {
-                    "local" => "LCL",
-                    "argument" => "ARG",
-                    "this" => "THIS",
-                    "that" => "THAT",
-                    _ => panic!("Unsupported segment name: {}", opcode.segment),
+                    Segment::Local => "LCL",
+                    Segment::Argument => "ARG",
+                    Segment::This => "THIS",
+                    _ => "THAT",
                 };
 
                 // R13 = @segment + i
@@ -249,18 +392,16 @@ impl Codegen {
                 self.emit("A=M");
                 self.emit("M=D");
             }
-            "constant" => {
-                panic!("pop constant is not supported");
-            }
-            "static" => {
+            Segment::Constant => unreachable!("the parser rejects pop constant"),
+            Segment::Static => {
                 self.emit_sp_dec();
                 self.emit_stack_to_d();
 
                 // @<filename>.<i> = D
-                self.emit(&format!("@{}.{}", &self.filename, opcode.i));
+                self.emit(&self.static_address(opcode.i));
                 self.emit("M=D");
             }
-            "temp" => {
+            Segment::Temp => {
                 // R13 = 5 + i
                 self.emit_constant_to_d(5);
                 self.emit(&format!("@{}", opcode.i));
@@ -274,11 +415,11 @@ impl Codegen {
                 self.emit("A=M");
                 self.emit("M=D");
             }
-            "pointer" => {
+            Segment::Pointer => {
                 let offset = match opcode.i {
                     0 => "THIS",
                     1 => "THAT",
-                    _ => panic!("Unknown pointer offset: {}", opcode.i),
+                    _ => unreachable!("the parser only accepts pointer 0 and 1"),
                 };
 
                 self.emit_sp_dec();
@@ -286,13 +427,23 @@ impl Codegen {
                 self.emit(&format!("@{}", offset));
                 self.emit("M=D");
             }
-            _ => panic!("Unknown segment name: {}", opcode.segment),
         }
     }
 
     fn emit_function(&mut self, opcode: &FunctionOpCode) {
         self.emit_comment(&format!("function {} {}", opcode.id, opcode.vars_count));
         self.emit_label(&format!("__CALL__{}__", opcode.id));
+        self.function = String::from(opcode.id);
+
+        if self.optimize && opcode.vars_count > 0 {
+            // Zeroes the local variables in place and moves SP past them once
+            self.emit_all(&["@SP", "A=M", "M=0"]);
+            for _ in 1..opcode.vars_count {
+                self.emit_all(&["A=A+1", "M=0"]);
+            }
+            self.emit_all(&["D=A+1", "@SP", "M=D"]);
+            return;
+        }
 
         let mut vars_count = opcode.vars_count;
         while vars_count > 0 {
@@ -307,10 +458,35 @@ impl Codegen {
     fn emit_call(&mut self, opcode: &CallOpCode) {
         self.emit_comment(&format!("call {} {}", opcode.id, opcode.args_count));
 
+        if self.optimize {
+            // R13 = number of arguments, R14 = function, D = return address
+            match opcode.args_count {
+                0 => self.emit_all(&["@R13", "M=0"]),
+                1 => self.emit_all(&["@R13", "M=1"]),
+                args_count => {
+                    self.emit_constant_to_d(args_count);
+                    self.emit_d_to_address("R13");
+                }
+            }
+            self.emit(&format!("@__CALL__{}__", opcode.id));
+            self.emit("D=A");
+            self.emit_d_to_address("R14");
+
+            let label = self.return_label();
+            self.emit(&format!("@{}", label));
+            self.emit("D=A");
+            self.emit("@__CALL");
+            self.emit("0;JMP");
+            self.emit(&format!("({})", label));
+            return;
+        }
+
         // store return address
         self.emit(&format!(
-            "@__CALL__{}__{}__RET",
-            opcode.id, self.label_counter
+            "@__CALL__{}__{}__{}__RET",
+            opcode.id,
+            self.file_label(),
+            self.label_counter
         ));
         self.emit("D=A");
         self.emit_d_to_stack();
@@ -347,8 +523,10 @@ impl Codegen {
         self.emit("0;JMP");
 
         self.emit_label(&format!(
-            "__CALL__{}__{}__RET",
-            opcode.id, self.label_counter
+            "__CALL__{}__{}__{}__RET",
+            opcode.id,
+            self.file_label(),
+            self.label_counter
         ));
 
         self.label_counter += 1;
@@ -357,6 +535,12 @@ impl Codegen {
     fn emit_return(&mut self) {
         self.emit_comment("return");
 
+        if self.optimize {
+            self.emit("@__RETURN");
+            self.emit("0;JMP");
+            return;
+        }
+
         // store the address of end frame and return address
         self.emit_address_to_d("LCL");
         self.emit_d_to_address("R13");
@@ -415,4 +599,373 @@ impl Codegen {
         self.emit("A=D");
         self.emit("0;JMP");
     }
+
+    // Points A to the cell of a segment, walking from its base when the index is small
+    fn emit_segment_address(&mut self, segment: Segment, i: u16) {
+        match segment {
+            Segment::Static => self.emit(&self.static_address(i)),
+            Segment::Temp => self.emit(&format!("@{}", i + 5)),
+            Segment::Pointer if i == 0 => self.emit("@THIS"),
+            Segment::Pointer => self.emit("@THAT"),
+            Segment::Constant => unreachable!("constants have no address"),
+            _ => {
+                self.emit(&format!("@{}", base_pointer(segment)));
+                match i {
+                    0 => self.emit("A=M"),
+                    i => {
+                        self.emit("A=M+1");
+                        for _ in 1..i {
+                            self.emit("A=A+1");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn emit_compact_push(&mut self, opcode: &PushOpCode) {
+        match (opcode.segment, opcode.i) {
+            (Segment::Constant, i @ 0..=1) => {
+                self.emit_all(&["@SP", "AM=M+1", "A=A-1"]);
+                self.emit(&format!("M={}", i));
+                return;
+            }
+            (Segment::Constant, i) => self.emit_constant_to_d(i),
+            (Segment::Local, i)
+            | (Segment::Argument, i)
+            | (Segment::This, i)
+            | (Segment::That, i)
+                if i > 2 =>
+            {
+                self.emit_address_to_d(base_pointer(opcode.segment));
+                self.emit(&format!("@{}", i));
+                self.emit_all(&["A=D+A", "D=M"]);
+            }
+            (segment, i) => {
+                self.emit_segment_address(segment, i);
+                self.emit("D=M");
+            }
+        }
+
+        self.emit_all(&["@SP", "AM=M+1", "A=A-1", "M=D"]);
+    }
+
+    fn emit_compact_pop(&mut self, opcode: &PopOpCode) {
+        match (opcode.segment, opcode.i) {
+            (Segment::Local, i)
+            | (Segment::Argument, i)
+            | (Segment::This, i)
+            | (Segment::That, i)
+                if i > 6 =>
+            {
+                // R13 = address of the cell
+                self.emit_address_to_d(base_pointer(opcode.segment));
+                self.emit(&format!("@{}", i));
+                self.emit("D=D+A");
+                self.emit_d_to_address("R13");
+
+                self.emit_all(&["@SP", "AM=M-1", "D=M", "@R13", "A=M", "M=D"]);
+            }
+            (segment, i) => {
+                self.emit_all(&["@SP", "AM=M-1", "D=M"]);
+                self.emit_segment_address(segment, i);
+                self.emit("M=D");
+            }
+        }
+    }
+
+    fn codegen_cached(&mut self, opcodes: &[OpCode]) -> String {
+        let mut index = 0;
+        while index < opcodes.len() {
+            index += match &opcodes[index..] {
+                [OpCode::Push(push), operation, rest @ ..]
+                    if is_binary(operation) && is_operand(push) =>
+                {
+                    self.emit_comment(&format!("push {} {}", push.segment, push.i));
+                    self.emit_comment(&operation.to_string());
+                    self.cache_top();
+                    let operand = self.emit_operand(push);
+                    2 + self.emit_cached_computation(operation, ("D", operand), rest)
+                }
+                [operation, rest @ ..] if is_binary(operation) => {
+                    self.emit_comment(&operation.to_string());
+                    self.cache_top();
+                    self.emit_all(&["@SP", "AM=M-1"]);
+                    1 + self.emit_cached_computation(operation, ("M", "D"), rest)
+                }
+                [opcode, ..] => {
+                    self.emit_cached(opcode);
+                    1
+                }
+                [] => unreachable!("the loop stops at the end"),
+            };
+        }
+
+        self.spill();
+        String::from(&self.assembly)
+    }
+
+    // Loads the top of the stack from RAM into D
+    fn cache_top(&mut self) {
+        if !self.cached {
+            self.emit_all(&["@SP", "AM=M-1", "D=M"]);
+            self.cached = true;
+        }
+    }
+
+    // Writes the top of the stack from D back to RAM
+    fn spill(&mut self) {
+        if self.cached {
+            self.emit_all(&["@SP", "AM=M+1", "A=A-1", "M=D"]);
+            self.cached = false;
+        }
+    }
+
+    // D = the value a push would put on the stack
+    fn emit_load(&mut self, push: &PushOpCode) {
+        match (push.segment, push.i) {
+            (Segment::Constant, i @ 0..=1) => self.emit(&format!("D={}", i)),
+            (Segment::Constant, i) => self.emit_constant_to_d(i),
+            (segment, i) if is_wide(segment, i, PUSH_WALK) => {
+                self.emit_address_to_d(base_pointer(segment));
+                self.emit(&format!("@{}", i));
+                self.emit_all(&["A=D+A", "D=M"]);
+            }
+            (segment, i) => {
+                self.emit_segment_address(segment, i);
+                self.emit("D=M");
+            }
+        }
+    }
+
+    // Stores D where a pop would
+    fn emit_store(&mut self, pop: &PopOpCode) {
+        if is_wide(pop.segment, pop.i, POP_WALK) {
+            // R13 = value, R14 = address of the cell
+            self.emit_d_to_address("R13");
+            self.emit_address_to_d(base_pointer(pop.segment));
+            self.emit(&format!("@{}", pop.i));
+            self.emit("D=D+A");
+            self.emit_d_to_address("R14");
+            self.emit_address_to_d("R13");
+            self.emit_all(&["@R14", "A=M", "M=D"]);
+        } else {
+            self.emit_segment_address(pop.segment, pop.i);
+            self.emit("M=D");
+        }
+    }
+
+    fn emit_move(&mut self, opcode: &MoveOpCode) {
+        self.emit_comment(&format!("push {} {}", opcode.from.segment, opcode.from.i));
+        self.emit_comment(&format!("pop {} {}", opcode.to.segment, opcode.to.i));
+        self.emit_load(&opcode.from);
+        self.emit_store(&opcode.to);
+    }
+
+    fn emit_cached(&mut self, opcode: &OpCode) {
+        match opcode {
+            OpCode::Push(push) => {
+                self.emit_comment(&format!("push {} {}", push.segment, push.i));
+                self.spill();
+                self.emit_load(push);
+                self.cached = true;
+            }
+            OpCode::Pop(pop) => {
+                self.emit_comment(&format!("pop {} {}", pop.segment, pop.i));
+                self.cache_top();
+                self.emit_store(pop);
+                self.cached = false;
+            }
+            OpCode::Neg | OpCode::Not => {
+                self.emit_comment(&opcode.to_string());
+                self.cache_top();
+                self.emit(if let OpCode::Neg = opcode {
+                    "D=-D"
+                } else {
+                    "D=!D"
+                });
+            }
+            OpCode::IfGoto(goto) => {
+                let id = self.scoped(goto.id);
+                self.emit_comment(&format!("if-goto {}", id));
+                self.cache_top();
+                self.emit(&format!("@{}", id));
+                self.emit("D;JNE");
+                self.cached = false;
+            }
+            // Labels are reached from other places, which have the whole stack in RAM
+            _ => {
+                self.spill();
+                match opcode {
+                    OpCode::Label(label) => self.emit_label(&self.scoped(label.id)),
+                    OpCode::Goto(goto) => self.emit_goto(&self.scoped(goto.id)),
+                    OpCode::Function(function) => self.emit_function(function),
+                    OpCode::Call(call) => self.emit_call(call),
+                    OpCode::Return => self.emit_return(),
+                    OpCode::Move(opcode) => self.emit_move(opcode),
+                    _ => unreachable!("other opcodes are handled above"),
+                }
+            }
+        }
+    }
+
+    // Points A to the operand, or loads a constant into it, returns which one of them to use
+    fn emit_operand(&mut self, push: &PushOpCode) -> &'static str {
+        if push.segment == Segment::Constant {
+            self.emit(&format!("@{}", push.i));
+            "A"
+        } else {
+            self.emit_segment_address(push.segment, push.i);
+            "M"
+        }
+    }
+
+    // Computes `left op right` into D, where the operands are D, A or M. Comparisons followed
+    // by `if-goto` jump right away instead of making a boolean, returns how many of the
+    // opcodes that follow were consumed that way
+    fn emit_cached_computation(
+        &mut self,
+        operation: &OpCode,
+        (left, right): (&str, &str),
+        rest: &[OpCode],
+    ) -> usize {
+        // Hack computes `D+M` but not `M+D`, these are the same anyway
+        let (left, right) = match operation {
+            OpCode::Add | OpCode::And | OpCode::Or if right == "D" => (right, left),
+            _ => (left, right),
+        };
+
+        let comparator = match operation {
+            OpCode::Eq => "JEQ",
+            OpCode::Gt => "JGT",
+            OpCode::Lt => "JLT",
+            _ => {
+                let operator = match operation {
+                    OpCode::Add => "+",
+                    OpCode::Sub => "-",
+                    OpCode::And => "&",
+                    _ => "|",
+                };
+                self.emit(&format!("D={}{}{}", left, operator, right));
+                return 0;
+            }
+        };
+
+        self.emit(&format!("D={}-{}", left, right));
+        match rest {
+            [OpCode::Not, OpCode::IfGoto(goto), ..] => {
+                let id = self.scoped(goto.id);
+                self.emit_comment("not");
+                self.emit_comment(&format!("if-goto {}", id));
+                self.emit(&format!("@{}", id));
+                self.emit(&format!("D;{}", inverse(comparator)));
+                self.cached = false;
+                2
+            }
+            [OpCode::IfGoto(goto), ..] => {
+                let id = self.scoped(goto.id);
+                self.emit_comment(&format!("if-goto {}", id));
+                self.emit(&format!("@{}", id));
+                self.emit(&format!("D;{}", comparator));
+                self.cached = false;
+                1
+            }
+            _ => {
+                let label = self.return_label();
+                self.emit(&format!("@{}__TRUE", label));
+                self.emit(&format!("D;{}", comparator));
+                self.emit("D=0");
+                self.emit(&format!("@{}__END", label));
+                self.emit("0;JMP");
+                self.emit(&format!("({}__TRUE)", label));
+                self.emit("D=-1");
+                self.emit(&format!("({}__END)", label));
+                0
+            }
+        }
+    }
+
+    fn emit_shared_routines(&mut self) {
+        self.emit_comment("shared routines");
+        self.emit("@__ROUTINES_END");
+        self.emit("0;JMP");
+
+        // Pushes the return address in D and the call frame, repositions ARG and LCL and jumps
+        // to the function in R14, R13 is the number of arguments
+        self.emit("(__CALL)");
+        self.emit_all(&["@SP", "A=M", "M=D"]);
+        for pointer in &["LCL", "ARG", "THIS", "THAT"] {
+            self.emit_address_to_d(pointer);
+            self.emit_all(&["@SP", "AM=M+1", "M=D"]);
+        }
+        self.emit_all(&["@SP", "MD=M+1", "@LCL", "M=D"]);
+        self.emit_all(&["@R13", "D=D-M", "@5", "D=D-A", "@ARG", "M=D"]);
+        self.emit_all(&["@R14", "A=M", "0;JMP"]);
+
+        // Moves the return value to ARG 0, restores the call frame and jumps back to the caller
+        self.emit("(__RETURN)");
+        self.emit_address_to_d("LCL");
+        self.emit_d_to_address("R13");
+        self.emit_all(&["@5", "A=D-A", "D=M", "@R14", "M=D"]);
+        self.emit_all(&["@SP", "AM=M-1", "D=M", "@ARG", "A=M", "M=D"]);
+        self.emit_all(&["@ARG", "D=M+1", "@SP", "M=D"]);
+        for pointer in &["THAT", "THIS", "ARG", "LCL"] {
+            self.emit_all(&["@R13", "AM=M-1", "D=M"]);
+            self.emit_d_to_address(pointer);
+        }
+        self.emit_all(&["@R14", "A=M", "0;JMP"]);
+
+        // Replaces the two values on top of the stack with the result of their comparison and
+        // jumps back to the return address in D
+        for comparator in &["JEQ", "JGT", "JLT"] {
+            self.emit(&format!("(__COMPARE_{})", comparator));
+            self.emit_d_to_address("R13");
+            self.emit_all(&["@SP", "AM=M-1", "D=M", "A=A-1", "D=M-D"]);
+            self.emit("@__COMPARE_TRUE");
+            self.emit(&format!("D;{}", comparator));
+            self.emit_all(&["@SP", "A=M-1", "M=0", "@R13", "A=M", "0;JMP"]);
+        }
+        self.emit("(__COMPARE_TRUE)");
+        self.emit_all(&["@SP", "A=M-1", "M=-1", "@R13", "A=M", "0;JMP"]);
+
+        self.emit("(__ROUTINES_END)");
+    }
+}
+
+fn base_pointer(segment: Segment) -> &'static str {
+    match segment {
+        Segment::Local => "LCL",
+        Segment::Argument => "ARG",
+        Segment::This => "THIS",
+        _ => "THAT",
+    }
+}
+
+// Cells past the first few of a segment are reached by adding the index to the base in D
+// rather than walking to them with `A=A+1`
+fn is_wide(segment: Segment, i: u16, walk: u16) -> bool {
+    match segment {
+        Segment::Local | Segment::Argument | Segment::This | Segment::That => i > walk,
+        _ => false,
+    }
+}
+
+fn is_binary(opcode: &OpCode) -> bool {
+    matches!(
+        opcode,
+        OpCode::Add | OpCode::Sub | OpCode::And | OpCode::Or | OpCode::Eq | OpCode::Gt | OpCode::Lt
+    )
+}
+
+// Pushes that can be an operand of the next computation without going through the stack
+fn is_operand(push: &PushOpCode) -> bool {
+    push.segment == Segment::Constant || !is_wide(push.segment, push.i, PUSH_WALK)
+}
+
+fn inverse(comparator: &str) -> &'static str {
+    match comparator {
+        "JEQ" => "JNE",
+        "JGT" => "JLE",
+        _ => "JGE",
+    }
 }
@@ -1,6 +1,26 @@
+use crate::validator::OS_FUNCTIONS;
+
 pub struct Config {
     pub input_file: String,
     pub output_file: String,
+    // Functions that may be called without being defined in the translated files
+    pub os_functions: Vec<String>,
+    pub optimize: bool,
+    pub cache_stack: bool,
+    // Whether the bootstrap code is emitted, by default only for directories
+    pub bootstrap: Option<bool>,
+    pub stack_pointer: u16,
+    // The function the bootstrap code calls, without one it runs into the translated code
+    pub entry: Option<String>,
+    // Initial values of LCL, ARG, THIS and THAT, set by the bootstrap code when given
+    pub local: Option<u16>,
+    pub argument: Option<u16>,
+    pub this: Option<u16>,
+    pub that: Option<u16>,
+    // First address of the static segment, without one the assembler allocates statics as
+    // variables from RAM 16
+    pub static_base: Option<u16>,
+    pub static_limit: u16,
 }
 
 impl Config {
@@ -8,9 +28,29 @@ impl Config {
         Config {
             input_file: String::from(input_file),
             output_file: String::from(output_file),
+            os_functions: OS_FUNCTIONS
+                .iter()
+                .map(|name| String::from(*name))
+                .collect(),
+            optimize: false,
+            cache_stack: false,
+            bootstrap: None,
+            stack_pointer: 256,
+            entry: Some(String::from("Sys.init")),
+            local: None,
+            argument: None,
+            this: None,
+            that: None,
+            static_base: None,
+            static_limit: 255,
         }
     }
 
+    // First and last address statics may take
+    pub fn statics(&self) -> (u16, u16) {
+        (self.static_base.unwrap_or(16), self.static_limit)
+    }
+
     pub fn from_args() -> Config {
         let matches = clap::App::new("vm")
             .version(clap::crate_version!())
@@ -18,6 +58,123 @@ impl Config {
             .about("Translator from stack virtual machine code to Hack Assembly")
             .arg_from_usage("-i --input=<INPUT-FILE> 'Set an input file where vm code persists'")
             .arg_from_usage("-o --output=<OUTPUT-FILE> 'Set an output file where translated code will be stored'")
+            .arg_from_usage("--os=[FUNCTION]... 'Treat a function as defined elsewhere, like the functions of the Jack OS'")
+            .arg_from_usage("-O --optimize 'Rewrite opcodes into shorter ones and share the code of calls, returns and comparisons'")
+            .arg_from_usage("-C --cache-stack 'Keep the top of the stack in D instead of RAM'")
+            .arg(
+                clap::Arg::from_usage("--bootstrap 'Emit the bootstrap code even for a single file'")
+                    .conflicts_with("no-bootstrap"),
+            )
+            .arg_from_usage("--no-bootstrap 'Do not emit the bootstrap code even for a directory'")
+            .arg(
+                clap::Arg::from_usage("--sp=[ADDRESS] 'Set the initial stack pointer in the bootstrap code'")
+                    .default_value("256"),
+            )
+            .arg(
+                clap::Arg::from_usage("--entry=[FUNCTION] 'Set the function the bootstrap code calls, Sys.init by default'")
+                    .conflicts_with("no-entry"),
+            )
+            .arg_from_usage("--no-entry 'Run into the translated code after the bootstrap code instead of calling a function'")
+            .arg_from_usage("--local=[ADDRESS] 'Set the initial LCL in the bootstrap code'")
+            .arg_from_usage("--argument=[ADDRESS] 'Set the initial ARG in the bootstrap code'")
+            .arg_from_usage("--this=[ADDRESS] 'Set the initial THIS in the bootstrap code'")
+            .arg_from_usage("--that=[ADDRESS] 'Set the initial THAT in the bootstrap code'")
+            .arg_from_usage("--static-base=[ADDRESS] 'Place statics from this address instead of leaving them to the assembler'")
+            .arg(
+                clap::Arg::from_usage("--static-limit=[ADDRESS] 'Set the last address statics may take'")
+                    .default_value("255"),
+            )
+            .get_matches();
+
+        let input_file = matches
+            .value_of("input")
+            .expect("Missing --input parameter");
+
+        let output_file = matches
+            .value_of("output")
+            .expect("Missing --output parameter");
+
+        let mut config = Config::new(input_file, output_file);
+        config.os_functions.extend(
+            matches
+                .values_of("os")
+                .unwrap_or_default()
+                .map(String::from),
+        );
+
+        config.optimize = matches.is_present("optimize");
+        config.cache_stack = matches.is_present("cache-stack");
+
+        if matches.is_present("bootstrap") {
+            config.bootstrap = Some(true);
+        } else if matches.is_present("no-bootstrap") {
+            config.bootstrap = Some(false);
+        }
+
+        config.stack_pointer = matches
+            .value_of("sp")
+            .and_then(parse_address)
+            .expect("--sp must be an address from 0 to 32767");
+        if matches.is_present("no-entry") {
+            config.entry = None;
+        } else if let Some(entry) = matches.value_of("entry") {
+            config.entry = Some(String::from(entry));
+        }
+
+        config.local = matches
+            .value_of("local")
+            .map(|local| parse_address(local).expect("--local must be an address from 0 to 32767"));
+        config.argument = matches.value_of("argument").map(|argument| {
+            parse_address(argument).expect("--argument must be an address from 0 to 32767")
+        });
+        config.this = matches
+            .value_of("this")
+            .map(|this| parse_address(this).expect("--this must be an address from 0 to 32767"));
+        config.that = matches
+            .value_of("that")
+            .map(|that| parse_address(that).expect("--that must be an address from 0 to 32767"));
+
+        config.static_base = matches.value_of("static-base").map(|base| {
+            parse_address(base).expect("--static-base must be an address from 0 to 32767")
+        });
+        config.static_limit = matches
+            .value_of("static-limit")
+            .and_then(parse_address)
+            .expect("--static-limit must be an address from 0 to 32767");
+
+        config
+    }
+}
+
+// Addresses are loaded with an A-instruction, which only takes 15 bits
+fn parse_address(address: &str) -> Option<u16> {
+    address
+        .trim()
+        .parse()
+        .ok()
+        .filter(|address| *address <= 32767)
+}
+
+pub struct OptimizerConfig {
+    pub input_file: String,
+    pub output_file: String,
+}
+
+impl OptimizerConfig {
+    pub fn new(input_file: &str, output_file: &str) -> OptimizerConfig {
+        OptimizerConfig {
+            input_file: String::from(input_file),
+            output_file: String::from(output_file),
+        }
+    }
+
+    pub fn from_args() -> OptimizerConfig {
+        let matches = clap::App::new("vmopt")
+            .version(clap::crate_version!())
+            .author("Eugene Obrezkov <ghaiklor@gmail.com>")
+            .about("Optimizer of stack virtual machine code, the result is VM code as well")
+            .arg_from_usage("-i --input=<INPUT-FILE> 'Set an input file where vm code persists'")
+            .arg_from_usage("-o --output=<OUTPUT-FILE> 'Set an output file where optimized code will be stored'")
             .get_matches();
 
         let input_file = matches
@@ -28,6 +185,6 @@ impl Config {
             .value_of("output")
             .expect("Missing --output parameter");
 
-        Config::new(input_file, output_file)
+        OptimizerConfig::new(input_file, output_file)
     }
 }
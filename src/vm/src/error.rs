@@ -0,0 +1,98 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnknownCommand(String),
+    MissingArgument(&'static str, &'static str),
+    UnexpectedArgument(String),
+    InvalidNumber(String),
+    UnknownSegment(String),
+    PopConstant,
+    InvalidPointer(u16),
+    InvalidTemp(u16),
+    UndefinedLabel(String),
+    UndefinedFunction(String),
+    UndefinedEntry(String),
+    TooManyStatics(usize, u16, u16),
+    StackUnderflow,
+    StackMismatch(usize, usize),
+    UnreadableFile,
+    UnwritableFile,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownCommand(command) => write!(f, "unknown command `{}`", command),
+            ErrorKind::MissingArgument(command, argument) => {
+                write!(f, "`{}` is missing its {}", command, argument)
+            }
+            ErrorKind::UnexpectedArgument(argument) => {
+                write!(f, "unexpected `{}` after the command", argument)
+            }
+            ErrorKind::InvalidNumber(number) => {
+                write!(f, "`{}` is not a number from 0 to 32767", number)
+            }
+            ErrorKind::UnknownSegment(segment) => write!(f, "unknown segment `{}`", segment),
+            ErrorKind::PopConstant => write!(f, "`pop constant` has nowhere to store the value"),
+            ErrorKind::InvalidPointer(i) => {
+                write!(f, "`pointer {}` does not exist, only 0 and 1 do", i)
+            }
+            ErrorKind::InvalidTemp(i) => write!(f, "`temp {}` does not exist, only 0 to 7 do", i),
+            ErrorKind::UndefinedLabel(label) => {
+                write!(f, "label `{}` is not defined in this function", label)
+            }
+            ErrorKind::UndefinedFunction(function) => {
+                write!(f, "function `{}` is not defined", function)
+            }
+            ErrorKind::UndefinedEntry(function) => write!(
+                f,
+                "the bootstrap code calls `{}`, which is not defined in the translated files, \
+                 pass --entry or --no-entry",
+                function
+            ),
+            ErrorKind::TooManyStatics(count, first, last) => write!(
+                f,
+                "{} static variables do not fit into RAM {} to {}",
+                count, first, last
+            ),
+            ErrorKind::StackUnderflow => write!(f, "pops a value the stack does not have"),
+            ErrorKind::StackMismatch(expected, found) => write!(
+                f,
+                "the stack has {} values when reached on one path and {} on another",
+                expected, found
+            ),
+            ErrorKind::UnreadableFile => write!(f, "could not read the file"),
+            ErrorKind::UnwritableFile => write!(f, "could not write the file"),
+        }
+    }
+}
+
+// The line is 0 for errors that belong to the whole file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub file: String,
+    pub line: usize,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, file: &str, line: usize) -> Error {
+        Error {
+            kind,
+            file: String::from(file),
+            line,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}: {}", self.file, self.kind),
+            line => write!(f, "{}:{}: {}", self.file, line, self.kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
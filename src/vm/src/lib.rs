@@ -1,41 +1,165 @@
+pub mod callgraph;
 pub mod codegen;
 pub mod config;
+pub mod error;
 pub mod opcode;
+pub mod optimize;
 pub mod parser;
+pub mod validator;
 
 use codegen::Codegen;
-use config::Config;
+use config::{Config, OptimizerConfig};
+use error::{Error, ErrorKind};
+use opcode::{OpCode, Statement};
 use std::fs;
-use std::fs::File;
-use std::io::Write;
-
-pub fn run(config: Config) {
-    let input_files: Vec<_> = if fs::metadata(&config.input_file).unwrap().is_dir() {
-        fs::read_dir(&config.input_file)
-            .unwrap()
-            .map(|entry| entry.unwrap().path())
+use std::path::Path;
+use std::process;
+
+pub fn run(config: Config) -> Result<(), Vec<Error>> {
+    let is_dir = Path::new(&config.input_file).is_dir();
+    let input_files: Vec<_> = if is_dir {
+        let entries = fs::read_dir(&config.input_file)
+            .map_err(|_| vec![Error::new(ErrorKind::UnreadableFile, &config.input_file, 0)])?;
+
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
             .filter(|path| path.is_file())
-            .filter(|path| path.extension().unwrap() == "vm")
-            .map(|path| path.to_str().unwrap().to_string())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "vm"))
+            .map(|path| path.to_string_lossy().to_string())
             .collect()
     } else {
         vec![String::from(&config.input_file)]
     };
 
-    let mut output_file = File::create(&config.output_file).expect("Can not create an output file");
-    if fs::metadata(&config.input_file).unwrap().is_dir() {
-        output_file
-            .write_all(Codegen::new(&config.output_file).emit_entry().as_bytes())
-            .expect("Could not write entry in assembly file");
+    let bootstrap = config.bootstrap.unwrap_or(is_dir);
+    let mut assembly = String::new();
+    if bootstrap {
+        assembly.push_str(&Codegen::new(&config.output_file, &config).emit_entry(&config));
+    } else if config.optimize {
+        assembly.push_str(&Codegen::new(&config.output_file, &config).emit_routines());
     }
 
+    // Every file is parsed even after an error, so that all of them are reported at once
+    let mut sources: Vec<(String, String)> = Vec::new();
+    let mut errors = Vec::new();
     for input_file in input_files {
-        let vm_code = fs::read_to_string(&input_file).expect("Could not read the input file");
-        let opcodes = parser::parse(&vm_code);
-        let assembly = Codegen::new(&input_file).codegen(&opcodes);
+        match fs::read_to_string(&input_file) {
+            Ok(vm_code) => sources.push((input_file, vm_code)),
+            Err(_) => errors.push(Error::new(ErrorKind::UnreadableFile, &input_file, 0)),
+        }
+    }
+
+    let mut files: Vec<(&str, Vec<Statement>)> = Vec::new();
+    for (input_file, vm_code) in &sources {
+        match parser::parse_statements(input_file, vm_code) {
+            Ok(statements) => files.push((input_file, statements)),
+            Err(file_errors) => errors.extend(file_errors),
+        }
+    }
+
+    // Calls to functions of files that failed to parse would be reported as undefined
+    if errors.is_empty() {
+        let files: Vec<(&str, &[Statement])> = files
+            .iter()
+            .map(|(file, statements)| (*file, statements.as_slice()))
+            .collect();
+        errors.extend(validator::validate(
+            &files,
+            &config.os_functions,
+            config.statics(),
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut files: Vec<(&str, Vec<OpCode>)> = files
+        .into_iter()
+        .map(|(file, statements)| {
+            let opcodes = statements
+                .into_iter()
+                .map(|statement| statement.opcode)
+                .collect();
+            (file, opcodes)
+        })
+        .collect();
+
+    // Only the bootstrap code says where the program starts. It is the one call that has to be
+    // translated along, even for OS functions, as nothing else would define its label
+    if let Some(entry) = config.entry.as_deref().filter(|_| bootstrap) {
+        if !callgraph::is_defined(&files, entry) {
+            let kind = ErrorKind::UndefinedEntry(String::from(entry));
+            return Err(vec![Error::new(kind, &config.input_file, 0)]);
+        }
+
+        let removed = callgraph::remove_unreachable(&mut files, entry);
+        if !removed.is_empty() {
+            eprintln!(
+                "note: removed {} function{} unreachable from `{}`: {}",
+                removed.len(),
+                if removed.len() == 1 { "" } else { "s" },
+                entry,
+                removed.join(", ")
+            );
+        }
+    }
+
+    let mut next_static = config.static_base;
+    for (input_file, mut opcodes) in files {
+        if config.optimize {
+            opcodes = optimize::optimize(opcodes);
+        }
+
+        let mut codegen = Codegen::new(input_file, &config);
+        if let Some(first) = next_static {
+            next_static = Some(codegen.place_statics(first, &opcodes));
+        }
+        assembly.push_str(&codegen.codegen(&opcodes));
+    }
+
+    fs::write(&config.output_file, assembly).map_err(|_| {
+        vec![Error::new(
+            ErrorKind::UnwritableFile,
+            &config.output_file,
+            0,
+        )]
+    })
+}
 
-        output_file
-            .write_all(assembly.as_bytes())
-            .expect("Could not write to the output file");
+// Optimizes a VM file into another one, moves are written back as `push` and `pop`
+pub fn optimize_file(config: OptimizerConfig) -> Result<(), Vec<Error>> {
+    let vm_code = fs::read_to_string(&config.input_file)
+        .map_err(|_| vec![Error::new(ErrorKind::UnreadableFile, &config.input_file, 0)])?;
+    let opcodes = parser::parse(&config.input_file, &vm_code)?;
+
+    let optimized: Vec<String> = optimize::optimize(opcodes)
+        .iter()
+        .map(|opcode| opcode.to_string())
+        .collect();
+
+    fs::write(&config.output_file, optimized.join("\n") + "\n").map_err(|_| {
+        vec![Error::new(
+            ErrorKind::UnwritableFile,
+            &config.output_file,
+            0,
+        )]
+    })
+}
+
+// Prints the errors and exits, `action` is what could not be done to the file
+pub fn abort(action: &str, file: &str, errors: &[Error]) -> ! {
+    for error in errors {
+        eprintln!("error: {}", error);
     }
+
+    eprintln!(
+        "error: could not {} `{}` due to {} previous error{}",
+        action,
+        file,
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" }
+    );
+
+    process::exit(1);
 }
@@ -2,5 +2,9 @@ use vm::config::Config;
 
 fn main() {
     let config = Config::from_args();
-    vm::run(config);
+    let input_file = config.input_file.clone();
+
+    if let Err(errors) = vm::run(config) {
+        vm::abort("translate", &input_file, &errors);
+    }
 }
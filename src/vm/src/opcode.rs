@@ -1,43 +1,100 @@
-#[derive(Debug)]
-pub struct PushOpCode<'a> {
-    pub segment: &'a str,
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Local,
+    Argument,
+    This,
+    That,
+    Constant,
+    Static,
+    Temp,
+    Pointer,
+}
+
+impl Segment {
+    pub fn from_name(name: &str) -> Option<Segment> {
+        match name {
+            "local" => Some(Segment::Local),
+            "argument" => Some(Segment::Argument),
+            "this" => Some(Segment::This),
+            "that" => Some(Segment::That),
+            "constant" => Some(Segment::Constant),
+            "static" => Some(Segment::Static),
+            "temp" => Some(Segment::Temp),
+            "pointer" => Some(Segment::Pointer),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Segment::Local => "local",
+            Segment::Argument => "argument",
+            Segment::This => "this",
+            Segment::That => "that",
+            Segment::Constant => "constant",
+            Segment::Static => "static",
+            Segment::Temp => "temp",
+            Segment::Pointer => "pointer",
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushOpCode {
+    pub segment: Segment,
     pub i: u16,
 }
 
-#[derive(Debug)]
-pub struct PopOpCode<'a> {
-    pub segment: &'a str,
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopOpCode {
+    pub segment: Segment,
     pub i: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LabelOpCode<'a> {
     pub id: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GotoOpCode<'a> {
     pub id: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IfGotoOpCode<'a> {
     pub id: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionOpCode<'a> {
     pub id: &'a str,
     pub vars_count: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CallOpCode<'a> {
     pub id: &'a str,
     pub args_count: u16,
 }
 
-#[derive(Debug)]
+// A value copied from one segment to another without going through the stack, what `push`
+// followed by `pop` does
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveOpCode {
+    pub from: PushOpCode,
+    pub to: PopOpCode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum OpCode<'a> {
     Add,
     Sub,
@@ -49,11 +106,49 @@ pub enum OpCode<'a> {
     Or,
     Not,
     Return,
-    Push(PushOpCode<'a>),
-    Pop(PopOpCode<'a>),
+    Push(PushOpCode),
+    Pop(PopOpCode),
     Label(LabelOpCode<'a>),
     Goto(GotoOpCode<'a>),
     IfGoto(IfGotoOpCode<'a>),
     Function(FunctionOpCode<'a>),
     Call(CallOpCode<'a>),
+    Move(MoveOpCode),
+}
+
+// VM code of the opcode, a move is written as the `push` and `pop` it stands for
+impl fmt::Display for OpCode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpCode::Add => write!(f, "add"),
+            OpCode::Sub => write!(f, "sub"),
+            OpCode::Neg => write!(f, "neg"),
+            OpCode::Eq => write!(f, "eq"),
+            OpCode::Gt => write!(f, "gt"),
+            OpCode::Lt => write!(f, "lt"),
+            OpCode::And => write!(f, "and"),
+            OpCode::Or => write!(f, "or"),
+            OpCode::Not => write!(f, "not"),
+            OpCode::Return => write!(f, "return"),
+            OpCode::Push(opcode) => write!(f, "push {} {}", opcode.segment, opcode.i),
+            OpCode::Pop(opcode) => write!(f, "pop {} {}", opcode.segment, opcode.i),
+            OpCode::Label(opcode) => write!(f, "label {}", opcode.id),
+            OpCode::Goto(opcode) => write!(f, "goto {}", opcode.id),
+            OpCode::IfGoto(opcode) => write!(f, "if-goto {}", opcode.id),
+            OpCode::Function(opcode) => write!(f, "function {} {}", opcode.id, opcode.vars_count),
+            OpCode::Call(opcode) => write!(f, "call {} {}", opcode.id, opcode.args_count),
+            OpCode::Move(opcode) => write!(
+                f,
+                "push {} {}\npop {} {}",
+                opcode.from.segment, opcode.from.i, opcode.to.segment, opcode.to.i
+            ),
+        }
+    }
+}
+
+// An opcode along with the line of the file it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement<'a> {
+    pub opcode: OpCode<'a>,
+    pub line: usize,
 }
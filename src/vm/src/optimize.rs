@@ -0,0 +1,165 @@
+use crate::opcode::*;
+use std::collections::HashSet;
+
+// Rewrites opcodes into fewer ones doing the same. Every rewrite removes opcodes, so the passes
+// are repeated until the code stops getting shorter
+pub fn optimize(opcodes: Vec<OpCode>) -> Vec<OpCode> {
+    let mut opcodes = opcodes;
+
+    loop {
+        let length = opcodes.len();
+        opcodes = remove_unused_labels(remove_dead_code(rewrite(opcodes)));
+
+        if opcodes.len() == length {
+            return opcodes;
+        }
+    }
+}
+
+// Peephole rewrites, applied to the end of the optimized code as every opcode is added to it,
+// so that the result of one rewrite is seen by the next one
+fn rewrite(opcodes: Vec<OpCode>) -> Vec<OpCode> {
+    let mut optimized: Vec<OpCode> = Vec::with_capacity(opcodes.len());
+
+    for opcode in opcodes {
+        optimized.push(opcode);
+
+        while let Some((length, replacement)) = rewrite_tail(&optimized) {
+            optimized.truncate(optimized.len() - length);
+            optimized.extend(replacement);
+        }
+    }
+
+    optimized
+}
+
+// How many opcodes at the end of the code are replaced and what with
+fn rewrite_tail<'a>(opcodes: &[OpCode<'a>]) -> Option<(usize, Vec<OpCode<'a>>)> {
+    let tail = &opcodes[opcodes.len().saturating_sub(3)..];
+
+    // Operations that can not be folded are left to the other rewrites
+    if let [.., OpCode::Push(a), OpCode::Push(b), operation] = tail {
+        if a.segment == Segment::Constant && b.segment == Segment::Constant {
+            if let Some(folded) = fold(a.i, b.i, operation) {
+                return Some((3, folded));
+            }
+        }
+    }
+
+    match tail {
+        [.., OpCode::Push(a), OpCode::Neg] if *a == constant(0) => Some((2, vec![push(0)])),
+        [.., OpCode::Push(a), OpCode::IfGoto(goto)] if a.segment == Segment::Constant => {
+            match a.i {
+                0 => Some((2, Vec::new())),
+                _ => Some((2, vec![OpCode::Goto(GotoOpCode { id: goto.id })])),
+            }
+        }
+        // Constants are positive, so the negation of any of them is true
+        [OpCode::Push(a), OpCode::Not, OpCode::IfGoto(goto)] if a.segment == Segment::Constant => {
+            Some((3, vec![OpCode::Goto(GotoOpCode { id: goto.id })]))
+        }
+        [.., OpCode::Not, OpCode::Not] => Some((2, Vec::new())),
+        // Values are equal when their difference is zero
+        [OpCode::Eq, OpCode::Not, OpCode::IfGoto(goto)] => Some((
+            3,
+            vec![OpCode::Sub, OpCode::IfGoto(IfGotoOpCode { id: goto.id })],
+        )),
+        [.., OpCode::Push(from), OpCode::Pop(to)] => {
+            if from.segment == to.segment && from.i == to.i {
+                Some((2, Vec::new()))
+            } else {
+                Some((
+                    2,
+                    vec![OpCode::Move(MoveOpCode {
+                        from: from.clone(),
+                        to: to.clone(),
+                    })],
+                ))
+            }
+        }
+        [.., OpCode::Goto(goto), OpCode::Label(label)] if goto.id == label.id => {
+            Some((2, vec![OpCode::Label(LabelOpCode { id: label.id })]))
+        }
+        _ => None,
+    }
+}
+
+// Computes an operation on two constants at compile time. Only results from 0 to 32767 can be
+// pushed as a constant, true is made by negating 0
+fn fold<'a>(a: u16, b: u16, operation: &OpCode) -> Option<Vec<OpCode<'a>>> {
+    let (x, y) = (a as i16, b as i16);
+    let value = match operation {
+        OpCode::Add => x.wrapping_add(y),
+        OpCode::Sub => x.wrapping_sub(y),
+        OpCode::And => x & y,
+        OpCode::Or => x | y,
+        OpCode::Eq => -((x == y) as i16),
+        OpCode::Gt => -((x > y) as i16),
+        OpCode::Lt => -((x < y) as i16),
+        _ => return None,
+    };
+
+    match value {
+        -1 => Some(vec![push(0), OpCode::Not]),
+        value if value >= 0 => Some(vec![push(value as u16)]),
+        _ => None,
+    }
+}
+
+fn constant(i: u16) -> PushOpCode {
+    PushOpCode {
+        segment: Segment::Constant,
+        i,
+    }
+}
+
+fn push<'a>(i: u16) -> OpCode<'a> {
+    OpCode::Push(constant(i))
+}
+
+// Nothing after `goto` or `return` runs until the next label or function
+fn remove_dead_code(opcodes: Vec<OpCode>) -> Vec<OpCode> {
+    let mut is_dead = false;
+
+    opcodes
+        .into_iter()
+        .filter(|opcode| {
+            match opcode {
+                OpCode::Label(_) | OpCode::Function(_) => is_dead = false,
+                _ if is_dead => return false,
+                OpCode::Goto(_) | OpCode::Return => is_dead = true,
+                _ => (),
+            }
+
+            true
+        })
+        .collect()
+}
+
+// Labels belong to the function they are defined in, so only jumps from the same function count
+fn remove_unused_labels<'a>(opcodes: Vec<OpCode<'a>>) -> Vec<OpCode<'a>> {
+    let mut targets: HashSet<(usize, &'a str)> = HashSet::new();
+    let mut function = 0;
+    for opcode in &opcodes {
+        match opcode {
+            OpCode::Function(_) => function += 1,
+            OpCode::Goto(GotoOpCode { id }) | OpCode::IfGoto(IfGotoOpCode { id }) => {
+                targets.insert((function, *id));
+            }
+            _ => (),
+        }
+    }
+
+    let mut function = 0;
+    opcodes
+        .into_iter()
+        .filter(|opcode| match opcode {
+            OpCode::Function(_) => {
+                function += 1;
+                true
+            }
+            OpCode::Label(label) => targets.contains(&(function, label.id)),
+            _ => true,
+        })
+        .collect()
+}
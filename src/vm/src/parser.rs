@@ -1,102 +1,123 @@
+use crate::error::*;
 use crate::opcode::*;
 
-pub fn parse(vm_code: &str) -> Vec<OpCode> {
-    let mut opcodes = Vec::new();
-
-    for instruction in vm_code.lines() {
-        let instruction = instruction.trim().splitn(2, '/').next().unwrap().trim();
-        if instruction.is_empty() {
-            continue;
-        }
-
-        if instruction.starts_with("push") {
-            let parts: Vec<&str> = instruction.splitn(3, ' ').collect();
-            let opcode = OpCode::Push(PushOpCode {
-                segment: parts[1],
-                i: parts[2]
-                    .parse()
-                    .expect("Expected a number in push segment i"),
-            });
-
-            opcodes.push(opcode);
-            continue;
-        };
-
-        if instruction.starts_with("pop") {
-            let parts: Vec<&str> = instruction.splitn(3, ' ').collect();
-            let opcode = OpCode::Pop(PopOpCode {
-                segment: parts[1],
-                i: parts[2]
-                    .parse()
-                    .expect("Expected a number in pop segment i"),
-            });
-
-            opcodes.push(opcode);
-            continue;
-        };
+pub fn parse<'a>(file: &str, vm_code: &'a str) -> Result<Vec<OpCode<'a>>, Vec<Error>> {
+    let statements = parse_statements(file, vm_code)?;
+    Ok(statements
+        .into_iter()
+        .map(|statement| statement.opcode)
+        .collect())
+}
 
-        if instruction.starts_with("goto") {
-            let parts: Vec<&str> = instruction.splitn(2, ' ').collect();
-            let opcode = OpCode::Goto(GotoOpCode { id: parts[1] });
+// Parses the whole file, reporting every malformed command along with its line
+pub fn parse_statements<'a>(
+    file: &str,
+    vm_code: &'a str,
+) -> Result<Vec<Statement<'a>>, Vec<Error>> {
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
 
-            opcodes.push(opcode);
+    for (index, line) in vm_code.lines().enumerate() {
+        let instruction = line.split("//").next().unwrap_or("").trim();
+        if instruction.is_empty() {
             continue;
         }
 
-        if instruction.starts_with("if-goto") {
-            let parts: Vec<&str> = instruction.splitn(2, ' ').collect();
-            let opcode = OpCode::IfGoto(IfGotoOpCode { id: parts[1] });
-
-            opcodes.push(opcode);
-            continue;
-        }
-
-        if instruction.starts_with("label") {
-            let parts: Vec<&str> = instruction.splitn(2, ' ').collect();
-            let opcode = OpCode::Label(LabelOpCode { id: parts[1] });
-
-            opcodes.push(opcode);
-            continue;
+        match parse_instruction(instruction) {
+            Ok(opcode) => statements.push(Statement {
+                opcode,
+                line: index + 1,
+            }),
+            Err(kind) => errors.push(Error::new(kind, file, index + 1)),
         }
+    }
 
-        if instruction.starts_with("function") {
-            let parts: Vec<&str> = instruction.splitn(3, ' ').collect();
-            let opcode = OpCode::Function(FunctionOpCode {
-                id: parts[1],
-                vars_count: parts[2].parse().expect("Number of variables expected"),
-            });
+    if errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(errors)
+    }
+}
 
-            opcodes.push(opcode);
-            continue;
+fn parse_instruction(instruction: &str) -> Result<OpCode<'_>, ErrorKind> {
+    let mut words = instruction.split_whitespace();
+    let command = words.next().unwrap_or("");
+
+    let opcode = match command {
+        "push" | "pop" => {
+            let name = words
+                .next()
+                .ok_or(ErrorKind::MissingArgument(command_name(command), "segment"))?;
+            let segment = Segment::from_name(name)
+                .ok_or_else(|| ErrorKind::UnknownSegment(String::from(name)))?;
+            let i = number(words.next(), command_name(command), "index")?;
+
+            match (command, segment, i) {
+                ("pop", Segment::Constant, _) => return Err(ErrorKind::PopConstant),
+                (_, Segment::Pointer, i) if i > 1 => return Err(ErrorKind::InvalidPointer(i)),
+                (_, Segment::Temp, i) if i > 7 => return Err(ErrorKind::InvalidTemp(i)),
+                ("push", _, _) => OpCode::Push(PushOpCode { segment, i }),
+                _ => OpCode::Pop(PopOpCode { segment, i }),
+            }
         }
+        "label" => OpCode::Label(LabelOpCode {
+            id: name(words.next(), "label")?,
+        }),
+        "goto" => OpCode::Goto(GotoOpCode {
+            id: name(words.next(), "goto")?,
+        }),
+        "if-goto" => OpCode::IfGoto(IfGotoOpCode {
+            id: name(words.next(), "if-goto")?,
+        }),
+        "function" => OpCode::Function(FunctionOpCode {
+            id: name(words.next(), "function")?,
+            vars_count: number(words.next(), "function", "number of local variables")?,
+        }),
+        "call" => OpCode::Call(CallOpCode {
+            id: name(words.next(), "call")?,
+            args_count: number(words.next(), "call", "number of arguments")?,
+        }),
+        "add" => OpCode::Add,
+        "sub" => OpCode::Sub,
+        "neg" => OpCode::Neg,
+        "eq" => OpCode::Eq,
+        "gt" => OpCode::Gt,
+        "lt" => OpCode::Lt,
+        "and" => OpCode::And,
+        "or" => OpCode::Or,
+        "not" => OpCode::Not,
+        "return" => OpCode::Return,
+        _ => return Err(ErrorKind::UnknownCommand(String::from(command))),
+    };
+
+    match words.next() {
+        Some(argument) => Err(ErrorKind::UnexpectedArgument(String::from(argument))),
+        None => Ok(opcode),
+    }
+}
 
-        if instruction.starts_with("call") {
-            let parts: Vec<&str> = instruction.splitn(3, ' ').collect();
-            let opcode = OpCode::Call(CallOpCode {
-                id: parts[1],
-                args_count: parts[2].parse().expect("Number of arguments expected"),
-            });
-
-            opcodes.push(opcode);
-            continue;
-        }
+fn command_name(command: &str) -> &'static str {
+    if command == "push" {
+        "push"
+    } else {
+        "pop"
+    }
+}
 
-        let opcode = match instruction {
-            "add" => OpCode::Add,
-            "sub" => OpCode::Sub,
-            "neg" => OpCode::Neg,
-            "eq" => OpCode::Eq,
-            "gt" => OpCode::Gt,
-            "lt" => OpCode::Lt,
-            "and" => OpCode::And,
-            "or" => OpCode::Or,
-            "not" => OpCode::Not,
-            "return" => OpCode::Return,
-            &_ => panic!("Unknown opcode {}", instruction),
-        };
+fn name<'a>(word: Option<&'a str>, command: &'static str) -> Result<&'a str, ErrorKind> {
+    word.ok_or(ErrorKind::MissingArgument(command, "name"))
+}
 
-        opcodes.push(opcode);
+// Numbers end up in A-instructions, so they have 15 bits
+fn number(
+    word: Option<&str>,
+    command: &'static str,
+    argument: &'static str,
+) -> Result<u16, ErrorKind> {
+    let word = word.ok_or(ErrorKind::MissingArgument(command, argument))?;
+
+    match word.parse::<u16>() {
+        Ok(number) if number <= 32767 => Ok(number),
+        _ => Err(ErrorKind::InvalidNumber(String::from(word))),
     }
-
-    opcodes
 }
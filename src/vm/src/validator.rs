@@ -0,0 +1,223 @@
+use crate::error::*;
+use crate::opcode::*;
+use std::collections::{HashMap, HashSet};
+
+// Functions of the Jack OS, which may be called without being translated along
+pub const OS_FUNCTIONS: [&str; 49] = [
+    "Math.init",
+    "Math.abs",
+    "Math.multiply",
+    "Math.divide",
+    "Math.min",
+    "Math.max",
+    "Math.sqrt",
+    "String.new",
+    "String.dispose",
+    "String.length",
+    "String.charAt",
+    "String.setCharAt",
+    "String.appendChar",
+    "String.eraseLastChar",
+    "String.intValue",
+    "String.setInt",
+    "String.backSpace",
+    "String.doubleQuote",
+    "String.newLine",
+    "Array.new",
+    "Array.dispose",
+    "Output.init",
+    "Output.moveCursor",
+    "Output.printChar",
+    "Output.printString",
+    "Output.printInt",
+    "Output.println",
+    "Output.backSpace",
+    "Screen.init",
+    "Screen.clearScreen",
+    "Screen.setColor",
+    "Screen.drawPixel",
+    "Screen.drawLine",
+    "Screen.drawRectangle",
+    "Screen.drawCircle",
+    "Keyboard.init",
+    "Keyboard.keyPressed",
+    "Keyboard.readChar",
+    "Keyboard.readLine",
+    "Keyboard.readInt",
+    "Memory.init",
+    "Memory.peek",
+    "Memory.poke",
+    "Memory.alloc",
+    "Memory.deAlloc",
+    "Sys.init",
+    "Sys.halt",
+    "Sys.error",
+    "Sys.wait",
+];
+
+// Checks files translated together, given as `(name, statements)`, for mistakes the
+// translator would otherwise turn into assembly that only breaks at run time. Statics have to
+// fit between the `statics` addresses, by default from RAM 16 up to 255, where the stack starts
+pub fn validate(
+    files: &[(&str, &[Statement])],
+    os_functions: &[String],
+    statics: (u16, u16),
+) -> Vec<Error> {
+    let (first, last) = statics;
+    let budget = (last as usize + 1).saturating_sub(first as usize);
+    let mut errors: Vec<Error> = Vec::new();
+    let defined: HashSet<&str> = files
+        .iter()
+        .flat_map(|(_, statements)| statements.iter())
+        .filter_map(|statement| match &statement.opcode {
+            OpCode::Function(function) => Some(function.id),
+            _ => None,
+        })
+        .chain(os_functions.iter().map(|name| name.as_str()))
+        .collect();
+
+    let mut allocated: HashSet<(&str, u16)> = HashSet::new();
+    for (file, statements) in files {
+        for statement in statements.iter() {
+            let kind = match &statement.opcode {
+                // Only the first static past the budget is reported
+                OpCode::Push(PushOpCode {
+                    segment: Segment::Static,
+                    i,
+                })
+                | OpCode::Pop(PopOpCode {
+                    segment: Segment::Static,
+                    i,
+                }) if allocated.insert((file, *i)) && allocated.len() == budget + 1 => {
+                    Some(ErrorKind::TooManyStatics(allocated.len(), first, last))
+                }
+                OpCode::Call(call) if !defined.contains(call.id) => {
+                    Some(ErrorKind::UndefinedFunction(String::from(call.id)))
+                }
+                _ => None,
+            };
+
+            if let Some(kind) = kind {
+                errors.push(Error::new(kind, file, statement.line));
+            }
+        }
+
+        // Code before the first function is checked on its own, as if it was a function
+        let mut start = 0;
+        for end in 1..=statements.len() {
+            if end == statements.len() || matches!(statements[end].opcode, OpCode::Function(_)) {
+                check_function(file, &statements[start..end], &mut errors);
+                start = end;
+            }
+        }
+    }
+
+    // Errors of the passes above are mixed, keep them in the order of lines
+    errors.sort_by_key(|error| {
+        let file = files.iter().position(|(name, _)| *name == error.file);
+        (file, error.line)
+    });
+    errors
+}
+
+// Labels must be defined in the function jumping to them, and every path to a statement must
+// leave the same number of values on the stack
+fn check_function(file: &str, statements: &[Statement], errors: &mut Vec<Error>) {
+    let labels: HashMap<&str, usize> = statements
+        .iter()
+        .enumerate()
+        .filter_map(|(index, statement)| match &statement.opcode {
+            OpCode::Label(label) => Some((label.id, index)),
+            _ => None,
+        })
+        .collect();
+
+    // Depth of the stack when a statement is reached, unreachable ones are not checked
+    let mut depths: Vec<Option<usize>> = vec![None; statements.len()];
+    let mut mismatches: HashSet<usize> = HashSet::new();
+    let mut pending: Vec<usize> = Vec::new();
+    if !statements.is_empty() {
+        depths[0] = Some(0);
+        pending.push(0);
+    }
+
+    for statement in statements {
+        if let OpCode::Goto(GotoOpCode { id }) | OpCode::IfGoto(IfGotoOpCode { id }) =
+            &statement.opcode
+        {
+            if !labels.contains_key(id) {
+                errors.push(Error::new(
+                    ErrorKind::UndefinedLabel(String::from(*id)),
+                    file,
+                    statement.line,
+                ));
+            }
+        }
+    }
+
+    while let Some(index) = pending.pop() {
+        let statement = &statements[index];
+        let depth = depths[index].unwrap_or(0);
+        let (pops, pushes) = effect(&statement.opcode);
+
+        if depth < pops {
+            errors.push(Error::new(ErrorKind::StackUnderflow, file, statement.line));
+            continue;
+        }
+
+        let after = depth - pops + pushes;
+        let next = index + 1;
+        let successors: Vec<usize> = match &statement.opcode {
+            OpCode::Goto(goto) => labels.get(goto.id).copied().into_iter().collect(),
+            OpCode::IfGoto(goto) => labels
+                .get(goto.id)
+                .copied()
+                .into_iter()
+                .chain(Some(next))
+                .collect(),
+            OpCode::Return => Vec::new(),
+            _ => vec![next],
+        };
+
+        for successor in successors {
+            if successor >= statements.len() {
+                continue;
+            }
+
+            match depths[successor] {
+                None => {
+                    depths[successor] = Some(after);
+                    pending.push(successor);
+                }
+                Some(expected) if expected != after => {
+                    if mismatches.insert(successor) {
+                        errors.push(Error::new(
+                            ErrorKind::StackMismatch(expected, after),
+                            file,
+                            statements[successor].line,
+                        ));
+                    }
+                }
+                Some(_) => (),
+            }
+        }
+    }
+}
+
+// How many values an opcode pops from the stack and how many it pushes
+fn effect(opcode: &OpCode) -> (usize, usize) {
+    match opcode {
+        OpCode::Push(_) => (0, 1),
+        OpCode::Pop(_) | OpCode::IfGoto(_) | OpCode::Return => (1, 0),
+        OpCode::Add
+        | OpCode::Sub
+        | OpCode::Eq
+        | OpCode::Gt
+        | OpCode::Lt
+        | OpCode::And
+        | OpCode::Or => (2, 1),
+        OpCode::Neg | OpCode::Not => (1, 1),
+        OpCode::Call(call) => (call.args_count as usize, 1),
+        OpCode::Label(_) | OpCode::Goto(_) | OpCode::Function(_) | OpCode::Move(_) => (0, 0),
+    }
+}
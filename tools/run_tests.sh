@@ -16,14 +16,18 @@ COMPILER_SPEC=$PROJECT_ROOT/spec/compiler
 CPU_SPEC=$PROJECT_ROOT/spec/cpu
 HASM_SPEC=$PROJECT_ROOT/spec/hasm
 VM_SPEC=$PROJECT_ROOT/spec/vm
+VM_BOOTSTRAP_SPEC=$PROJECT_ROOT/spec/vm-bootstrap
+VMOPT_SPEC=$PROJECT_ROOT/spec/vmopt
 OS_SPEC=$PROJECT_ROOT/spec/os
 CPU_SOURCES=$PROJECT_ROOT/src/cpu
 COMPUTER_SOURCES=$CPU_SOURCES/computer
 OS_SOURCES=$PROJECT_ROOT/src/os
 
 COMPILER_EXECUTABLE=$PROJECT_ROOT/target/debug/compiler
+HACK_EMU_EXECUTABLE=$PROJECT_ROOT/target/debug/hack-emu
 HASM_EXECUTABLE=$PROJECT_ROOT/target/debug/hasm
 VM_EXECUTABLE=$PROJECT_ROOT/target/debug/vm
+VMOPT_EXECUTABLE=$PROJECT_ROOT/target/debug/vmopt
 
 SUCCESS_COLOR="\033[32m"
 PENDING_COLOR="\033[33m"
@@ -66,7 +70,7 @@ function cpu_spec() {
             continue
         fi
 
-        "$PROJECT_TOOLS_ROOT"/cpu_emulator.sh "$tst_file" > /dev/null 2>&1
+        "$HACK_EMU_EXECUTABLE" --input "$tst_file" > /dev/null
         success "🙂 $(basename "$tst_file")"
     done
 
@@ -78,8 +82,8 @@ function cpu_spec() {
             continue
         fi
 
-        pending "🕰 Simulating $(basename "$tst_file")"
-        "$PROJECT_TOOLS_ROOT"/hardware_simulator.sh "$tst_file" > /dev/null 2>&1
+        "$HACK_EMU_EXECUTABLE" --input "$tst_file" > /dev/null
+        success "🙂 $(basename "$tst_file")"
     done
 }
 
@@ -98,47 +102,140 @@ function hasm_spec() {
         mv "$hack_file" "$COMPUTER_SOURCES/$(basename "$hack_file")"
         success "🙂 $(basename "$hack_file") -> $COMPUTER_SOURCES/$(basename "$hack_file")"
     done
+
+    echo
+    header "Testing macros and includes in $HASM_SPEC/include"
+    "$HASM_EXECUTABLE" --input "$HASM_SPEC/include/Main.asm" --output "$HASM_SPEC/include/Main.hack"
+    "$HACK_EMU_EXECUTABLE" --input "$HASM_SPEC/include/Main.tst" > /dev/null
+    success "🙂 Main.tst"
 }
 
-# Running tests for the Project 7, 8
+# Running tests for the Project 7, 8, translated with every set of translator options
 function vm_spec() {
+    for flags in "" "--optimize" "--cache-stack" "--optimize --cache-stack"; do
+        vm_spec_with "$VM_SPEC" "$flags"
+    done
+}
+
+function vm_spec_with() {
+    local spec=$1
+    local flags=$2
+    local hasm_flags=${3:-}
     echo
 
-    header "Translating $VM_SPEC"
-    for vm_file in "$VM_SPEC"/**/*.vm; do
+    header "Translating $spec with options: ${flags:-none}"
+    for vm_file in "$spec"/**/*.vm; do
         # These are the cases when we need to translate the whole directory
         if [[ $(dirname "$vm_file") =~ fibonacci-element ]]; then
-            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/FibonacciElement.asm"
+            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/FibonacciElement.asm" $flags
             success "🙂 $(basename "$vm_file")"
             continue
         fi
 
         if [[ $(dirname "$vm_file") =~ nested-call ]]; then
-            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/NestedCall.asm"
+            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/NestedCall.asm" $flags
             success "🙂 $(basename "$vm_file")"
             continue
         fi
 
         if [[ $(dirname "$vm_file") =~ statics-test ]]; then
-            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/StaticsTest.asm"
+            "$VM_EXECUTABLE" --input "$(dirname "$vm_file")" --output "$(dirname "$vm_file")/StaticsTest.asm" $flags
             success "🙂 $(basename "$vm_file")"
             continue
         fi
 
-        "$VM_EXECUTABLE" --input "$vm_file" --output "$(dirname "$vm_file")/$(basename "$vm_file" .vm).asm"
+        "$VM_EXECUTABLE" --input "$vm_file" --output "$(dirname "$vm_file")/$(basename "$vm_file" .vm).asm" $flags
         success "🙂 $(basename "$vm_file")"
     done
 
-    header "Running tests for $VM_SPEC"
-    for tst_file in "$VM_SPEC"/**/*.tst; do
-        pending "🕰 Simulating $(basename "$tst_file")"
+    # Scripts load the assembly, so it is assembled with the options and disassembled back
+    if [[ -n $hasm_flags ]]; then
+        header "Assembling $spec with options: $hasm_flags"
+        for asm_file in "$spec"/**/*.asm; do
+            hack_file="$(dirname "$asm_file")/$(basename "$asm_file" .asm).hack"
+            "$HASM_EXECUTABLE" --input "$asm_file" --output "$hack_file" --no-warnings $hasm_flags
+            "$HASM_EXECUTABLE" --input "$hack_file" --output "$asm_file" --disassemble
+            success "🙂 $(basename "$asm_file")"
+        done
+    fi
+
+    header "Running tests for $spec with options: ${flags:-none}"
+    for tst_file in "$spec"/**/*.tst; do
+        "$HACK_EMU_EXECUTABLE" --input "$tst_file" > /dev/null
+        success "🙂 $(basename "$tst_file")"
+    done
+}
+
+# The bootstrap code sets up the segments instead of the test script
+function vm_bootstrap_spec() {
+    echo
+
+    header "Translating $VM_SPEC/basic-test with the bootstrap code into $VM_BOOTSTRAP_SPEC"
+    "$VM_EXECUTABLE" --input "$VM_SPEC/basic-test/BasicTest.vm" --output "$VM_BOOTSTRAP_SPEC/BasicTest.asm" \
+        --bootstrap --no-entry --local 300 --argument 400 --this 3000 --that 3010
+    success "🙂 BasicTest.vm"
+
+    header "Running tests for $VM_BOOTSTRAP_SPEC"
+    "$HACK_EMU_EXECUTABLE" --input "$VM_BOOTSTRAP_SPEC/BasicTest.tst" > /dev/null
+    success "🙂 BasicTest.tst"
+}
+
+# Running the CPU and VM spec programs again, assembled with the peephole optimizer
+function hasm_optimize_spec() {
+    optimized_spec=$(mktemp -d)
+    cp -r "$CPU_SPEC"/. "$optimized_spec"
 
-        if [[ $tst_file =~ VME.tst ]]; then
-            "$PROJECT_TOOLS_ROOT"/vm_emulator.sh "$tst_file" > /dev/null 2>&1
-        else
-            "$PROJECT_TOOLS_ROOT"/cpu_emulator.sh "$tst_file" > /dev/null 2>&1
+    echo
+    header "Assembling $CPU_SPEC into $optimized_spec with options: --optimize"
+    for asm_file in "$optimized_spec"/**/*.asm; do
+        "$HASM_EXECUTABLE" --input "$asm_file" --output "$(dirname "$asm_file")/$(basename "$asm_file" .asm).hack" --optimize
+        success "🙂 $(basename "$asm_file")"
+    done
+
+    echo
+    header "Testing $optimized_spec"
+    for tst_file in "$optimized_spec"/**/*.tst; do
+        # Fill.tst can not be tested automatically
+        if [[ $tst_file =~ Fill.tst ]]; then
+            continue
         fi
+
+        "$HACK_EMU_EXECUTABLE" --input "$tst_file" > /dev/null
+        success "🙂 $(basename "$tst_file")"
+    done
+
+    rm -rf "$optimized_spec"
+    optimized_spec=$(mktemp -d)
+    cp -r "$VM_SPEC"/. "$optimized_spec"
+
+    vm_spec_with "$optimized_spec" "" "--optimize"
+    rm -rf "$optimized_spec"
+}
+
+# Optimizing VM code into VM code, the spec programs have to pass their tests after it
+function vmopt_spec() {
+    echo
+
+    header "Optimizing $VMOPT_SPEC"
+    for vm_file in "$VMOPT_SPEC"/*.vm; do
+        cmp_file="$(dirname "$vm_file")/$(basename "$vm_file" .vm).cmp"
+        "$VMOPT_EXECUTABLE" --input "$vm_file" --output "$(dirname "$vm_file")/$(basename "$vm_file" .vm).out"
+        diff "$(dirname "$vm_file")/$(basename "$vm_file" .vm).out" "$cmp_file"
+        success "🙂 $(basename "$vm_file")"
+    done
+
+    optimized_spec=$(mktemp -d)
+    cp -r "$VM_SPEC"/. "$optimized_spec"
+
+    echo
+    header "Optimizing $VM_SPEC into $optimized_spec"
+    for vm_file in "$optimized_spec"/**/*.vm; do
+        "$VMOPT_EXECUTABLE" --input "$vm_file" --output "$vm_file"
+        success "🙂 $(basename "$vm_file")"
     done
+
+    vm_spec_with "$optimized_spec" ""
+    rm -rf "$optimized_spec"
 }
 
 function compiler_spec() {
@@ -180,14 +277,17 @@ function os_spec () {
 
     header "Running tests for $OS_SPEC"
     for tst_file in "$OS_SPEC"/**/*.tst; do
-        pending "🕰 Simulating $(basename "$tst_file")"
-        "$PROJECT_TOOLS_ROOT"/vm_emulator.sh "$tst_file" > /dev/null 2>&1
+        "$HACK_EMU_EXECUTABLE" --input "$tst_file" > /dev/null
+        success "🙂 $(basename "$tst_file")"
     done
 }
 
 hasm_spec
 cpu_spec
 vm_spec
+vm_bootstrap_spec
+hasm_optimize_spec
+vmopt_spec
 compiler_spec
 os_spec
 